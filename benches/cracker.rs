@@ -0,0 +1,282 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use egui::ahash::HashSet;
+use many_time_pad::{
+    windows1250_words, ByteSet, Cipher, Cracker, LanguageModel, LanguagePack, PotentialKey,
+    TextEncoding, WordMatcher, WORDS,
+};
+use std::num::NonZeroUsize;
+
+/// Size of the generated ciphertexts, small in debug builds where `cargo test` runs every
+/// benchmark once.
+const INPUT_SIZE: usize = if cfg!(debug_assertions) {
+    16 * 1024
+} else {
+    2 * 1024 * 1024
+};
+
+/// Key lengths cracked in the benchmarks.
+const KEY_LENGTHS: [usize; 2] = [256, 1024];
+
+/// Xorshift generator, deterministic so runs are comparable.
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+/// Polish text of random dictionary words encrypted with a random key.
+fn ciphertext(key_length: usize) -> Vec<u8> {
+    encrypt(key_length).0
+}
+
+/// Polish text of random dictionary words encrypted with a random key, and the key.
+fn encrypt(key_length: usize) -> (Vec<u8>, Vec<u8>) {
+    let mut random = Random(0x2545_F491_4F6C_DD1D);
+    let words: Vec<Vec<u8>> = WORDS
+        .split_whitespace()
+        .filter_map(|word| TextEncoding::WINDOWS1250.encode(word))
+        .collect();
+
+    let mut plaintext = Vec::with_capacity(INPUT_SIZE);
+    while plaintext.len() < INPUT_SIZE {
+        plaintext.extend(&words[random.next() as usize % words.len()]);
+        plaintext.push(if random.next() % 10 == 0 { b'.' } else { b' ' });
+    }
+    plaintext.truncate(INPUT_SIZE);
+
+    let key: Vec<u8> = (0..key_length).map(|_| random.next() as u8).collect();
+    let contents = Cipher::new(key.clone())
+        .expect("key is not empty")
+        .encrypt(&plaintext);
+    (contents, key)
+}
+
+fn sets(c: &mut Criterion) {
+    let mut random = Random(0x9E37_79B9_7F4A_7C15);
+    let bytes: Vec<Vec<u8>> = (0..64)
+        .map(|_| (0..128).map(|_| random.next() as u8).collect())
+        .collect();
+    let hash_sets: Vec<HashSet<u8>> = bytes
+        .iter()
+        .map(|set| set.iter().copied().collect())
+        .collect();
+    let byte_sets: Vec<ByteSet> = bytes.iter().map(|set| set.iter().collect()).collect();
+
+    let mut group = c.benchmark_group("sets");
+    group.bench_function("hash set intersection", |b| {
+        b.iter(|| {
+            hash_sets
+                .iter()
+                .fold((0..=255u8).collect::<HashSet<u8>>(), |set, other| {
+                    set.intersection(other).copied().collect()
+                })
+        })
+    });
+    group.bench_function("byte set intersection", |b| {
+        b.iter(|| {
+            byte_sets
+                .iter()
+                .fold(ByteSet::FULL, |set, other| set & *other)
+        })
+    });
+    group.bench_function("hash set contains", |b| {
+        b.iter(|| {
+            (0..=255u8)
+                .filter(|byte| hash_sets.iter().all(|set| set.contains(black_box(byte))))
+                .count()
+        })
+    });
+    group.bench_function("byte set contains", |b| {
+        b.iter(|| {
+            (0..=255u8)
+                .filter(|byte| byte_sets.iter().all(|set| set.contains(black_box(*byte))))
+                .count()
+        })
+    });
+    group.bench_function("hash set iteration", |b| {
+        b.iter(|| {
+            hash_sets
+                .iter()
+                .flatten()
+                .map(|byte| *byte as usize)
+                .sum::<usize>()
+        })
+    });
+    group.bench_function("byte set iteration", |b| {
+        b.iter(|| {
+            byte_sets
+                .iter()
+                .flatten()
+                .map(|byte| byte as usize)
+                .sum::<usize>()
+        })
+    });
+    group.bench_function("hash set intersection count", |b| {
+        b.iter(|| {
+            hash_sets
+                .windows(2)
+                .map(|pair| pair[0].intersection(&pair[1]).count())
+                .sum::<usize>()
+        })
+    });
+    group.bench_function("byte set intersection count", |b| {
+        b.iter(|| {
+            byte_sets
+                .windows(2)
+                .map(|pair| (pair[0] & pair[1]).len())
+                .sum::<usize>()
+        })
+    });
+    group.finish();
+}
+
+/// Bytes of every column of the contents, one column per key position.
+fn columns(contents: &[u8], key_length: usize) -> Vec<Vec<u8>> {
+    let mut columns = vec![Vec::new(); key_length];
+    for (index, byte) in contents.iter().enumerate() {
+        columns[index % key_length].push(*byte);
+    }
+    columns
+}
+
+/// Column attack and unigram ranking as before byte sets, with hash set candidates.
+fn hash_set_attack(alphabet: &HashSet<u8>, model: &LanguageModel, columns: &[Vec<u8>]) -> Vec<u8> {
+    columns
+        .iter()
+        .map(|column| {
+            let mut bytes = [false; 256];
+            for byte in column {
+                bytes[*byte as usize] = true;
+            }
+            let candidates: HashSet<u8> = (0..=255u8)
+                .filter(|key| {
+                    (0..=255u8)
+                        .filter(|byte| bytes[*byte as usize])
+                        .all(|byte| alphabet.contains(&(byte ^ key)))
+                })
+                .collect();
+            best_candidate(model, column, candidates.iter().copied())
+        })
+        .collect()
+}
+
+/// Column attack and unigram ranking with byte set candidates, like the cracker.
+fn byte_set_attack(key_bytes: &[ByteSet], model: &LanguageModel, columns: &[Vec<u8>]) -> Vec<u8> {
+    columns
+        .iter()
+        .map(|column| {
+            let bytes: ByteSet = column.iter().collect();
+            let candidates = bytes
+                .iter()
+                .fold(ByteSet::FULL, |key, byte| key & key_bytes[byte as usize]);
+            best_candidate(model, column, candidates.iter())
+        })
+        .collect()
+}
+
+/// Candidate decrypting the column to the most likely unigrams, zero if there is none.
+fn best_candidate(
+    model: &LanguageModel,
+    column: &[u8],
+    candidates: impl Iterator<Item = u8>,
+) -> u8 {
+    candidates
+        .map(|key| {
+            let score: f64 = column.iter().map(|byte| model.unigram(byte ^ key)).sum();
+            (key, score)
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map_or(0, |(key, _)| key)
+}
+
+fn crack(c: &mut Criterion) {
+    let encoding = TextEncoding::WINDOWS1250;
+    let language = LanguagePack::polish();
+    let cracker = Cracker::new(&encoding);
+    let model = LanguageModel::new(&language, &encoding);
+    let byte_alphabet = language.alphabet(&encoding);
+    let alphabet: HashSet<u8> = byte_alphabet.iter().collect();
+    let key_bytes: Vec<ByteSet> = (0..=255u8).map(|byte| byte_alphabet.xor(byte)).collect();
+
+    let mut group = c.benchmark_group("crack");
+    group.sample_size(10);
+    for key_length in KEY_LENGTHS {
+        let contents = ciphertext(key_length);
+        let columns = columns(&contents, key_length);
+        group.bench_with_input(
+            BenchmarkId::new("hash set column attack", key_length),
+            &columns,
+            |b, columns| b.iter(|| hash_set_attack(&alphabet, &model, columns)),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("byte set column attack", key_length),
+            &columns,
+            |b, columns| b.iter(|| byte_set_attack(&key_bytes, &model, columns)),
+        );
+        let key_length = NonZeroUsize::new(key_length).expect("key length is positive");
+        group.bench_with_input(
+            BenchmarkId::new("cracker", key_length),
+            &contents,
+            |b, contents| b.iter(|| cracker.crack(contents, key_length)),
+        );
+    }
+    group.finish();
+}
+
+fn word_matching(c: &mut Criterion) {
+    let words = windows1250_words();
+    let matcher = WordMatcher::new(&words);
+    let (contents, key) = encrypt(KEY_LENGTHS[0]);
+    // Every key position keeps the key byte and a few wrong candidates, like after the attacks.
+    let mut random = Random(0x9E37_79B9_7F4A_7C15);
+    let potential_key = PotentialKey::new(
+        key.iter()
+            .map(|value| {
+                let mut values = vec![*value];
+                values.extend((0..random.next() % 4).map(|_| random.next() as u8));
+                values
+            })
+            .collect(),
+    );
+    let key_length = key.len();
+
+    let scan =
+        || {
+            let mut matches = 0;
+            for word in &words {
+                for index in 0..contents.len().saturating_sub(word.len()) {
+                    if word.iter().zip(&contents[index..]).enumerate().all(
+                        |(i, (byte, encrypted))| {
+                            potential_key.is_possible((index + i) % key_length, byte ^ encrypted)
+                        },
+                    ) {
+                        matches += 1;
+                    }
+                }
+            }
+            matches
+        };
+    let search = || {
+        let mut matches = 0;
+        let mut search = matcher.search();
+        for (offset, byte) in contents[..contents.len() - 1].iter().enumerate() {
+            let possible = potential_key.get_possible_set(offset % key_length);
+            search.push(|value| possible.contains(value ^ byte), |_, _| matches += 1);
+        }
+        matches
+    };
+
+    let mut group = c.benchmark_group("word matching");
+    group.sample_size(10);
+    group.bench_function("word by word", |b| b.iter(scan));
+    group.bench_function("single pass", |b| b.iter(search));
+    group.finish();
+}
+
+criterion_group!(benches, sets, crack, word_matching);
+criterion_main!(benches);
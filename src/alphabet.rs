@@ -0,0 +1,201 @@
+use crate::{ByteSet, TextEncoding};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// Extension of alphabet files.
+pub const ALPHABET_EXTENSION: &str = "alphabet";
+
+/// Character classes usable in alphabet expressions.
+pub const ALPHABET_CLASSES: [&str; 6] = [
+    "letters",
+    "lower",
+    "upper",
+    "digits",
+    "punctuation",
+    "whitespace",
+];
+
+/// Set of characters that may appear in the plaintext.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Alphabet {
+    characters: BTreeSet<char>,
+}
+
+/// Error while parsing an alphabet expression.
+#[derive(Debug, PartialEq, Eq)]
+pub enum AlphabetError {
+    /// Class name is not one of `ALPHABET_CLASSES`.
+    UnknownClass(String),
+    /// Class is missing its closing `:]`.
+    Unterminated,
+    /// Expression ends with a lone backslash.
+    Escape,
+    /// Expression contains no characters.
+    Empty,
+}
+
+impl Alphabet {
+    /// Parse an expression of explicit characters and classes like `[:letters:]`.
+    /// A backslash escapes the next character, `\n`, `\r` and `\t` stand for control characters.
+    pub fn parse(expression: &str) -> Result<Self, AlphabetError> {
+        let mut characters = BTreeSet::new();
+        let mut rest = expression;
+
+        while let Some(character) = rest.chars().next() {
+            if let Some(class) = rest.strip_prefix("[:") {
+                let end = class.find(":]").ok_or(AlphabetError::Unterminated)?;
+                characters.extend(class_characters(&class[..end])?);
+                rest = &class[end + 2..];
+            } else if let Some(escaped) = rest.strip_prefix('\\') {
+                let character = escaped.chars().next().ok_or(AlphabetError::Escape)?;
+                characters.insert(match character {
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    character => character,
+                });
+                rest = &escaped[character.len_utf8()..];
+            } else {
+                characters.insert(character);
+                rest = &rest[character.len_utf8()..];
+            }
+        }
+
+        if characters.is_empty() {
+            Err(AlphabetError::Empty)
+        } else {
+            Ok(Self { characters })
+        }
+    }
+
+    /// Parse an alphabet file, line breaks separate parts of the expression.
+    pub fn load(text: &str) -> Result<Self, AlphabetError> {
+        Self::parse(&text.lines().collect::<String>())
+    }
+
+    /// Alphabet of exactly the given characters.
+    pub fn literal(text: &str) -> Self {
+        Self {
+            characters: text.chars().collect(),
+        }
+    }
+
+    /// Characters of the alphabet in ascending order.
+    pub fn characters(&self) -> impl Iterator<Item = char> + '_ {
+        self.characters.iter().copied()
+    }
+
+    /// Bytes of the characters in the given encoding.
+    /// Characters the encoding cannot represent are skipped.
+    pub fn bytes(&self, encoding: &TextEncoding) -> ByteSet {
+        self.sequences(encoding).into_iter().flatten().collect()
+    }
+
+    /// Every character encoded as its byte sequence in the given encoding.
+    /// Characters the encoding cannot represent are skipped.
+    pub fn sequences(&self, encoding: &TextEncoding) -> Vec<Vec<u8>> {
+        self.characters()
+            .filter_map(|character| encoding.encode(character.encode_utf8(&mut [0; 4])))
+            .collect()
+    }
+
+    /// Characters the given encoding cannot represent.
+    pub fn unencodable(&self, encoding: &TextEncoding) -> Vec<char> {
+        self.characters()
+            .filter(|character| {
+                encoding
+                    .encode(character.encode_utf8(&mut [0; 4]))
+                    .is_none()
+            })
+            .collect()
+    }
+}
+
+impl FromStr for Alphabet {
+    type Err = AlphabetError;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        Self::parse(expression)
+    }
+}
+
+fn class_characters(name: &str) -> Result<Vec<char>, AlphabetError> {
+    Ok(match name {
+        "letters" => ('a'..='z').chain('A'..='Z').collect(),
+        "lower" => ('a'..='z').collect(),
+        "upper" => ('A'..='Z').collect(),
+        "digits" => ('0'..='9').collect(),
+        "punctuation" => ('!'..='~').filter(char::is_ascii_punctuation).collect(),
+        "whitespace" => vec![' ', '\t', '\r', '\n'],
+        _ => return Err(AlphabetError::UnknownClass(name.to_owned())),
+    })
+}
+
+impl Display for AlphabetError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AlphabetError::UnknownClass(name) => write!(
+                f,
+                "Unknown class [:{name}:], use one of {}",
+                ALPHABET_CLASSES.join(", ")
+            ),
+            AlphabetError::Unterminated => write!(f, "Class is missing its closing :]"),
+            AlphabetError::Escape => write!(f, "Backslash at the end of the alphabet"),
+            AlphabetError::Empty => write!(f, "Alphabet is empty"),
+        }
+    }
+}
+
+impl std::error::Error for AlphabetError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn characters(expression: &str) -> String {
+        Alphabet::parse(expression).unwrap().characters().collect()
+    }
+
+    #[test]
+    fn parse_expands_classes_and_escapes() {
+        assert_eq!(characters("[:digits:]"), "0123456789");
+        assert_eq!(characters("ab[:upper:]ą"), {
+            let mut expected: String = ('A'..='Z').collect();
+            expected.push_str("abą");
+            expected
+        });
+        assert_eq!(characters("[:whitespace:]"), "\t\n\r ");
+        assert_eq!(characters("x[:lower:]"), ('a'..='z').collect::<String>());
+        assert_eq!(characters(r"\n\r\t\\\a"), "\t\n\r\\a");
+        assert_eq!(characters(r"\[:digits:]"), ":[]dgist");
+    }
+
+    #[test]
+    fn parse_keeps_literal_brackets() {
+        assert_eq!(characters("[]"), "[]");
+        assert_eq!(characters("a[b"), "[ab");
+        assert_eq!(characters("[a:]"), ":[]a");
+        assert_eq!(characters("[:digits:]["), "0123456789[");
+    }
+
+    #[test]
+    fn parse_rejects_malformed_expressions() {
+        assert_eq!(
+            Alphabet::parse("ab[:digits"),
+            Err(AlphabetError::Unterminated)
+        );
+        assert_eq!(Alphabet::parse("[:"), Err(AlphabetError::Unterminated));
+        assert_eq!(Alphabet::parse(r"ab\"), Err(AlphabetError::Escape));
+        assert_eq!(
+            Alphabet::parse("[:vowels:]"),
+            Err(AlphabetError::UnknownClass("vowels".to_owned()))
+        );
+        assert_eq!(Alphabet::parse(""), Err(AlphabetError::Empty));
+        assert_eq!(
+            Alphabet::load("a\n[:digits:]\n"),
+            Alphabet::parse("a[:digits:]")
+        );
+    }
+}
@@ -0,0 +1,11 @@
+//! Web worker cracking ciphertexts for the web application.
+
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    many_time_pad::ui::worker_main();
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    eprintln!("The worker is only used by the web application.");
+}
@@ -0,0 +1,335 @@
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign};
+
+/// A set of bytes stored as 256 bits.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ByteSet([u64; 4]);
+
+/// Iterator over bytes of a set in ascending order.
+#[derive(Clone)]
+pub struct ByteSetIter {
+    words: [u64; 4],
+    word: usize,
+}
+
+impl ByteSet {
+    /// Set of no bytes.
+    pub const EMPTY: ByteSet = ByteSet([0; 4]);
+
+    /// Set of all 256 bytes.
+    pub const FULL: ByteSet = ByteSet([u64::MAX; 4]);
+
+    /// Create an empty set.
+    pub fn new() -> Self {
+        Self::EMPTY
+    }
+
+    /// Is the byte in the set.
+    #[inline]
+    pub fn contains(&self, byte: u8) -> bool {
+        self.0[(byte >> 6) as usize] & (1 << (byte & 63)) != 0
+    }
+
+    /// Add the byte, returns false if it was already in the set.
+    #[inline]
+    pub fn insert(&mut self, byte: u8) -> bool {
+        let added = !self.contains(byte);
+        self.0[(byte >> 6) as usize] |= 1 << (byte & 63);
+        added
+    }
+
+    /// Remove the byte, returns false if it was not in the set.
+    #[inline]
+    pub fn remove(&mut self, byte: u8) -> bool {
+        let removed = self.contains(byte);
+        self.0[(byte >> 6) as usize] &= !(1 << (byte & 63));
+        removed
+    }
+
+    /// Number of bytes in the set.
+    #[inline]
+    pub fn len(&self) -> usize {
+        let [a, b, c, d] = self.0;
+        (a.count_ones() + b.count_ones() + c.count_ones() + d.count_ones()) as usize
+    }
+
+    /// Is the set empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0 == [0; 4]
+    }
+
+    /// Smallest byte of the set.
+    pub fn first(&self) -> Option<u8> {
+        self.iter().next()
+    }
+
+    /// Is every byte of this set also in the other one.
+    #[inline]
+    pub fn is_subset(&self, other: &ByteSet) -> bool {
+        *self & *other == *self
+    }
+
+    /// Keep only the bytes the predicate accepts.
+    pub fn retain(&mut self, mut predicate: impl FnMut(u8) -> bool) {
+        for byte in self.iter() {
+            if !predicate(byte) {
+                self.remove(byte);
+            }
+        }
+    }
+
+    /// Set of every byte XORed with the value.
+    pub fn xor(&self, value: u8) -> ByteSet {
+        self.iter().map(|byte| byte ^ value).collect()
+    }
+
+    /// Iterate over bytes in ascending order.
+    pub fn iter(&self) -> ByteSetIter {
+        ByteSetIter {
+            words: self.0,
+            word: 0,
+        }
+    }
+}
+
+impl Iterator for ByteSetIter {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        while self.word < 4 {
+            let bits = self.words[self.word];
+            if bits != 0 {
+                self.words[self.word] = bits & (bits - 1);
+                return Some((self.word * 64) as u8 + bits.trailing_zeros() as u8);
+            }
+            self.word += 1;
+        }
+        None
+    }
+
+    fn fold<B, F: FnMut(B, u8) -> B>(self, init: B, mut f: F) -> B {
+        let mut accumulator = init;
+        for (index, mut bits) in self.words.into_iter().enumerate().skip(self.word) {
+            while bits != 0 {
+                accumulator = f(
+                    accumulator,
+                    (index * 64) as u8 + bits.trailing_zeros() as u8,
+                );
+                bits &= bits - 1;
+            }
+        }
+        accumulator
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = ByteSet(self.words).len();
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for ByteSetIter {}
+
+impl IntoIterator for ByteSet {
+    type Item = u8;
+    type IntoIter = ByteSetIter;
+
+    fn into_iter(self) -> ByteSetIter {
+        self.iter()
+    }
+}
+
+impl IntoIterator for &ByteSet {
+    type Item = u8;
+    type IntoIter = ByteSetIter;
+
+    fn into_iter(self) -> ByteSetIter {
+        self.iter()
+    }
+}
+
+impl FromIterator<u8> for ByteSet {
+    fn from_iter<T: IntoIterator<Item = u8>>(iter: T) -> Self {
+        let mut set = ByteSet::EMPTY;
+        set.extend(iter);
+        set
+    }
+}
+
+impl<'a> FromIterator<&'a u8> for ByteSet {
+    fn from_iter<T: IntoIterator<Item = &'a u8>>(iter: T) -> Self {
+        iter.into_iter().copied().collect()
+    }
+}
+
+impl Extend<u8> for ByteSet {
+    fn extend<T: IntoIterator<Item = u8>>(&mut self, iter: T) {
+        for byte in iter {
+            self.insert(byte);
+        }
+    }
+}
+
+impl BitAnd for ByteSet {
+    type Output = ByteSet;
+
+    #[inline]
+    fn bitand(mut self, other: ByteSet) -> ByteSet {
+        self &= other;
+        self
+    }
+}
+
+impl BitAndAssign for ByteSet {
+    #[inline]
+    fn bitand_assign(&mut self, other: ByteSet) {
+        for (word, other) in self.0.iter_mut().zip(other.0) {
+            *word &= other;
+        }
+    }
+}
+
+impl BitOr for ByteSet {
+    type Output = ByteSet;
+
+    #[inline]
+    fn bitor(mut self, other: ByteSet) -> ByteSet {
+        self |= other;
+        self
+    }
+}
+
+impl BitOrAssign for ByteSet {
+    #[inline]
+    fn bitor_assign(&mut self, other: ByteSet) {
+        for (word, other) in self.0.iter_mut().zip(other.0) {
+            *word |= other;
+        }
+    }
+}
+
+impl std::fmt::Debug for ByteSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set()
+            .entries(self.iter().map(|byte| format!("{:02X}", byte)))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    /// Bytes at the ends of the 64-bit words.
+    const EDGES: [u8; 6] = [0, 63, 64, 127, 128, 255];
+
+    /// Sets covering edge bytes, single words and scattered bytes.
+    fn samples() -> Vec<BTreeSet<u8>> {
+        let mut samples = vec![
+            BTreeSet::new(),
+            EDGES.into_iter().collect(),
+            (0..=255).collect(),
+            (64..128).collect(),
+            [255].into_iter().collect(),
+        ];
+        for step in [3u8, 7, 37] {
+            samples.push((0..=255u8).filter(|byte| byte % step == 1).collect());
+            samples.push(
+                (0..=255u8)
+                    .map(|byte| byte.wrapping_mul(step))
+                    .take(40)
+                    .collect(),
+            );
+        }
+        samples
+    }
+
+    fn byte_set(bytes: &BTreeSet<u8>) -> ByteSet {
+        bytes.iter().collect()
+    }
+
+    #[test]
+    fn insert_remove_and_contains_match_btree_set() {
+        let mut set = ByteSet::new();
+        let mut expected = BTreeSet::new();
+        for byte in EDGES.into_iter().chain(EDGES).chain([1, 62, 65, 200]) {
+            assert_eq!(set.insert(byte), expected.insert(byte), "insert {byte}");
+            assert_eq!(set.len(), expected.len());
+        }
+        for byte in [63, 63, 0, 2, 255, 128, 129] {
+            assert_eq!(set.remove(byte), expected.remove(&byte), "remove {byte}");
+            assert_eq!(set.len(), expected.len());
+        }
+        for byte in 0..=255 {
+            assert_eq!(
+                set.contains(byte),
+                expected.contains(&byte),
+                "contains {byte}"
+            );
+        }
+        assert_eq!(set.first(), expected.first().copied());
+        assert!(!set.is_empty());
+        assert!(ByteSet::EMPTY.is_empty());
+        assert_eq!(ByteSet::FULL.len(), 256);
+    }
+
+    #[test]
+    fn partly_consumed_iterators_match_btree_set() {
+        for expected in samples() {
+            let expected: Vec<u8> = expected.into_iter().collect();
+            let set: ByteSet = expected.iter().collect();
+
+            for consumed in 0..=expected.len() {
+                let mut iter = set.iter();
+                for byte in &expected[..consumed] {
+                    assert_eq!(iter.next(), Some(*byte));
+                }
+                let rest = &expected[consumed..];
+                assert_eq!(iter.len(), rest.len());
+                assert_eq!(iter.size_hint(), (rest.len(), Some(rest.len())));
+                assert_eq!(iter.clone().collect::<Vec<u8>>(), rest);
+                let folded = iter.fold(Vec::new(), |mut bytes, byte| {
+                    bytes.push(byte);
+                    bytes
+                });
+                assert_eq!(folded, rest);
+            }
+        }
+    }
+
+    #[test]
+    fn set_operations_match_btree_set() {
+        let samples = samples();
+        for first in &samples {
+            let set = byte_set(first);
+
+            for value in EDGES.into_iter().chain([1, 0x5A]) {
+                let expected: BTreeSet<u8> = first.iter().map(|byte| byte ^ value).collect();
+                assert_eq!(set.xor(value), byte_set(&expected), "xor {value}");
+            }
+
+            // Stateful predicate checks every byte is visited once in ascending order.
+            let mut visited = Vec::new();
+            let mut retained = set;
+            retained.retain(|byte| {
+                visited.push(byte);
+                visited.len() % 2 == 0 || EDGES.contains(&byte)
+            });
+            assert_eq!(visited, first.iter().copied().collect::<Vec<u8>>());
+            let expected: BTreeSet<u8> = first
+                .iter()
+                .enumerate()
+                .filter(|(index, byte)| (index + 1) % 2 == 0 || EDGES.contains(byte))
+                .map(|(_, byte)| *byte)
+                .collect();
+            assert_eq!(retained, byte_set(&expected));
+
+            for second in &samples {
+                let other = byte_set(second);
+                assert_eq!(set & other, byte_set(&(first & second)));
+                assert_eq!(set | other, byte_set(&(first | second)));
+                assert_eq!(set.is_subset(&other), first.is_subset(second));
+            }
+        }
+    }
+}
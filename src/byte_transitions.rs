@@ -0,0 +1,90 @@
+/// Byte pairs that may follow each other in a text made of the given encoded characters.
+/// Used for encodings where a character spans several bytes, like UTF-8 or UTF-16.
+pub struct ByteTransitions {
+    pairs: Vec<bool>,
+    starts: [bool; 256],
+    ends: [bool; 256],
+}
+
+impl ByteTransitions {
+    /// Create transitions of characters given as byte sequences.
+    /// Returns none if every character is a single byte, then any pair is possible.
+    pub fn new(characters: &[Vec<u8>]) -> Option<Self> {
+        if characters.iter().all(|character| character.len() <= 1) {
+            return None;
+        }
+
+        let mut transitions = Self {
+            pairs: vec![false; 256 * 256],
+            starts: [false; 256],
+            ends: [false; 256],
+        };
+
+        for character in characters {
+            if let (Some(first), Some(last)) = (character.first(), character.last()) {
+                transitions.starts[*first as usize] = true;
+                transitions.ends[*last as usize] = true;
+            }
+            for pair in character.windows(2) {
+                transitions.pairs[pair_index(pair[0], pair[1])] = true;
+            }
+        }
+
+        // Any character can follow any other.
+        for end in (0..=255u8).filter(|byte| transitions.ends[*byte as usize]) {
+            for start in (0..=255u8).filter(|byte| transitions.starts[*byte as usize]) {
+                transitions.pairs[pair_index(end, start)] = true;
+            }
+        }
+
+        Some(transitions)
+    }
+
+    /// Can the second byte follow the first one.
+    pub fn is_allowed(&self, first: u8, second: u8) -> bool {
+        self.pairs[pair_index(first, second)]
+    }
+
+    /// Can a character start with the byte.
+    pub fn is_start(&self, byte: u8) -> bool {
+        self.starts[byte as usize]
+    }
+
+    /// Can a character end with the byte.
+    pub fn is_end(&self, byte: u8) -> bool {
+        self.ends[byte as usize]
+    }
+}
+
+fn pair_index(first: u8, second: u8) -> usize {
+    ((first as usize) << 8) | second as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Alphabet, TextEncoding};
+
+    #[test]
+    fn utf8_letters_stay_whole() {
+        let characters = Alphabet::literal("aą ").sequences(&TextEncoding::UTF8);
+        let transitions = ByteTransitions::new(&characters).expect("ą takes two bytes");
+        let [lead, continuation] = [0xC4, 0x85];
+
+        assert!(transitions.is_start(b'a') && transitions.is_start(lead));
+        assert!(!transitions.is_start(continuation));
+        assert!(transitions.is_end(continuation) && !transitions.is_end(lead));
+        assert!(transitions.is_allowed(lead, continuation));
+        assert!(transitions.is_allowed(continuation, b'a'));
+        assert!(transitions.is_allowed(b' ', lead));
+        assert!(!transitions.is_allowed(lead, b'a'));
+        assert!(!transitions.is_allowed(b'a', continuation));
+        assert!(!transitions.is_allowed(lead, lead));
+    }
+
+    #[test]
+    fn single_byte_characters_need_no_transitions() {
+        let characters = Alphabet::literal("aą ").sequences(&TextEncoding::WINDOWS1250);
+        assert!(ByteTransitions::new(&characters).is_none());
+    }
+}
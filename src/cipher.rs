@@ -1,23 +1,46 @@
+use crate::Error;
+use std::io::{Read, Write};
+
 /// A simple XOR cipher.
 pub struct Cipher {
     key: Vec<u8>,
 }
 
+/// A reader encrypting or decrypting everything read from the inner reader.
+pub struct CipherReader<'a, R> {
+    cipher: &'a Cipher,
+    inner: R,
+    offset: usize,
+}
+
+/// A writer encrypting or decrypting everything written before passing it to the inner writer.
+pub struct CipherWriter<'a, W> {
+    cipher: &'a Cipher,
+    inner: W,
+    offset: usize,
+    buffer: Vec<u8>,
+}
+
 impl Cipher {
-    /// Create a new cipher with the given key.
-    pub fn new(key: Vec<u8>) -> Self {
-        Self { key }
+    /// Create a new cipher with the given key, which must not be empty.
+    pub fn new(key: Vec<u8>) -> Result<Self, Error> {
+        if key.is_empty() {
+            Err(Error::EmptyKey)
+        } else {
+            Ok(Self { key })
+        }
+    }
+
+    /// Get the key of the cipher.
+    pub fn key(&self) -> &[u8] {
+        &self.key
     }
 
     /// Encrypt the given input.
     #[must_use]
     pub fn encrypt(&self, input: &[u8]) -> Vec<u8> {
-        let mut output = Vec::with_capacity(input.len());
-
-        for (index, byte) in input.iter().enumerate() {
-            output.push(byte ^ self.key[index % self.key.len()]);
-        }
-
+        let mut output = input.to_vec();
+        self.apply_at(0, &mut output);
         output
     }
 
@@ -27,10 +50,160 @@ impl Cipher {
     pub fn decrypt(&self, input: &[u8]) -> Vec<u8> {
         self.encrypt(input)
     }
+
+    /// Encrypt or decrypt in place bytes starting at the given offset of the stream.
+    pub fn apply_at(&self, offset: usize, bytes: &mut [u8]) {
+        let key = self.key.iter().cycle().skip(offset % self.key.len());
+        for (byte, key) in bytes.iter_mut().zip(key) {
+            *byte ^= key;
+        }
+    }
+
+    /// Encrypt or decrypt a stream while it is read.
+    pub fn reader<R: Read>(&self, inner: R) -> CipherReader<'_, R> {
+        CipherReader {
+            cipher: self,
+            inner,
+            offset: 0,
+        }
+    }
+
+    /// Encrypt or decrypt a stream while it is written.
+    pub fn writer<W: Write>(&self, inner: W) -> CipherWriter<'_, W> {
+        CipherWriter {
+            cipher: self,
+            inner,
+            offset: 0,
+            buffer: Vec::new(),
+        }
+    }
 }
 
 impl Default for Cipher {
     fn default() -> Self {
-        Self::new(vec![0])
+        Self { key: vec![0] }
+    }
+}
+
+impl<R> CipherReader<'_, R> {
+    /// Get the inner reader back.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for CipherReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.cipher.apply_at(self.offset, &mut buf[..read]);
+        self.offset += read;
+        Ok(read)
+    }
+}
+
+impl<W> CipherWriter<'_, W> {
+    /// Get the inner writer back.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for CipherWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.buffer.clear();
+        self.buffer.extend_from_slice(buf);
+        self.cipher.apply_at(self.offset, &mut self.buffer);
+        // Only the bytes accepted by the inner writer move the offset.
+        let written = self.inner.write(&self.buffer)?;
+        self.offset += written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A writer accepting at most a few bytes per call, like a full pipe.
+    struct ShortWriter {
+        bytes: Vec<u8>,
+        limit: usize,
+    }
+
+    impl Write for ShortWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            let written = buf.len().min(self.limit);
+            self.bytes.extend_from_slice(&buf[..written]);
+            Ok(written)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn input() -> Vec<u8> {
+        (0..1000).map(|index| (index * 7 % 251) as u8).collect()
+    }
+
+    #[test]
+    fn streams_match_encrypt_in_any_chunks() {
+        let cipher = Cipher::new(vec![3, 14, 15, 92, 65]).unwrap();
+        let input = input();
+        let expected = cipher.encrypt(&input);
+
+        for chunk in [1, 2, 3, 5, 7, 64, 999, 1000, 4096] {
+            let mut reader = cipher.reader(input.as_slice());
+            let mut output = Vec::new();
+            let mut buffer = vec![0; chunk];
+            loop {
+                let read = reader.read(&mut buffer).unwrap();
+                if read == 0 {
+                    break;
+                }
+                output.extend_from_slice(&buffer[..read]);
+            }
+            assert_eq!(output, expected, "reading {chunk} bytes at a time");
+
+            for limit in [1, 4, chunk] {
+                let mut writer = cipher.writer(ShortWriter {
+                    bytes: Vec::new(),
+                    limit,
+                });
+                for part in input.chunks(chunk) {
+                    writer.write_all(part).unwrap();
+                }
+                assert_eq!(
+                    writer.into_inner().bytes,
+                    expected,
+                    "writing {chunk} bytes at a time, {limit} accepted"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn apply_at_matches_encrypt() {
+        let cipher = Cipher::new(vec![3, 14, 15, 92, 65]).unwrap();
+        let input = input();
+        let expected = cipher.encrypt(&input);
+
+        for offset in [0, 1, 4, 5, 6, 13, 500, 999] {
+            for length in [0, 1, 5, 17] {
+                let end = (offset + length).min(input.len());
+                let mut bytes = input[offset..end].to_vec();
+                cipher.apply_at(offset, &mut bytes);
+                assert_eq!(bytes, expected[offset..end], "offset {offset}");
+            }
+        }
+    }
+
+    #[test]
+    fn empty_key_is_rejected() {
+        assert_eq!(Cipher::new(Vec::new()).err(), Some(Error::EmptyKey));
     }
 }
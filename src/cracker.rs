@@ -1,96 +1,528 @@
-use crate::{PotentialKey, TextEncoding};
-use egui::ahash::{HashMap, HashMapExt, HashSet, HashSetExt};
+use crate::{
+    parallel, Alphabet, ByteSet, ByteTransitions, Error, LanguageModel, LanguagePack, PotentialKey,
+    TextEncoding, WordMatcher,
+};
+use egui::ahash::HashSet;
+use serde::{Deserialize, Serialize};
 use std::num::NonZeroUsize;
+use std::ops::Range;
+
+/// Number of ciphertext bytes in a chunk searched for words, chunks are searched independently.
+const WORD_MATCHING_CHUNK: usize = 1 << 12;
 
 /// A cracker for a many time pad.
 pub struct Cracker {
-    combinations: HashMap<u8, HashSet<u8>>,
+    alphabet: ByteSet,
+    /// Key bytes decrypting a ciphertext byte to the alphabet, indexed by the ciphertext byte.
+    key_bytes: Vec<ByteSet>,
     words: Vec<Vec<u8>>,
+    /// Vote of every word for the key bytes it implies.
+    weights: Vec<f64>,
+    matcher: WordMatcher,
+    model: LanguageModel,
+    transitions: Option<ByteTransitions>,
+}
+
+/// Phase of cracking reported with progress.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CrackPhase {
+    /// Narrowing key bytes to those decrypting to the alphabet.
+    XorAttack,
+    /// Choosing key bytes that decrypt to dictionary words.
+    WordMatching,
+    /// Cracking under every encoding to find the most probable one.
+    EncodingDetection,
+}
+
+impl std::fmt::Display for CrackPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CrackPhase::XorAttack => write!(f, "XOR attack"),
+            CrackPhase::WordMatching => write!(f, "Word matching"),
+            CrackPhase::EncodingDetection => write!(f, "Encoding detection"),
+        }
+    }
+}
+
+/// Callback receiving progress of a phase from 0 to 1. Cracking is cancelled when it returns false.
+pub type CrackProgress<'a> = &'a mut dyn FnMut(CrackPhase, f32) -> bool;
+
+/// Ciphertext byte in a key column with its neighbours in the message.
+struct ColumnByte {
+    previous: Option<u8>,
+    byte: u8,
+    next: Option<u8>,
 }
 
 impl Cracker {
-    /// Create a new cracker with the given alphabet.
+    /// Create a new cracker for Polish texts in the given encoding.
     pub fn new(encoding: &TextEncoding) -> Self {
-        let alphabet = encoding.alphabet();
-        let words = encoding.words();
-
-        let mut combinations = HashMap::new();
-        for first in &alphabet {
-            for second in &alphabet {
-                let value = *first ^ *second;
-                combinations
-                    .entry(value)
-                    .or_insert_with(HashSet::new)
-                    .insert(*first);
-                combinations
-                    .entry(value)
-                    .or_insert_with(HashSet::new)
-                    .insert(*second);
-            }
-        }
+        Self::with_language(encoding, &LanguagePack::polish())
+    }
+
+    /// Create a new cracker for texts in the given language and encoding.
+    pub fn with_language(encoding: &TextEncoding, language: &LanguagePack) -> Self {
+        Self::with_alphabet(encoding, language, &Alphabet::literal(&language.alphabet))
+    }
+
+    /// Create a new cracker for texts in the given language and encoding,
+    /// limited to the given alphabet instead of the language alphabet.
+    pub fn with_alphabet(
+        encoding: &TextEncoding,
+        language: &LanguagePack,
+        alphabet: &Alphabet,
+    ) -> Self {
+        let (words, weights) = word_weights(language.words_with_frequencies(encoding));
+        let transitions = ByteTransitions::new(&alphabet.sequences(encoding));
+        let alphabet = alphabet.bytes(encoding);
         Self {
-            combinations,
+            key_bytes: (0..=255u8).map(|byte| alphabet.xor(byte)).collect(),
+            alphabet,
+            model: LanguageModel::new(language, encoding),
+            matcher: WordMatcher::new(&words),
             words,
+            weights,
+            transitions,
         }
     }
 
     /// Crack the given contents with the given key length.
-    /// Returns none if the key length is wrong or alphabet is wrong.
-    pub fn crack(&self, contents: &[u8], key_length: NonZeroUsize) -> PotentialKey {
-        let potential_key = self.xor_attack(contents, key_length);
-        let potential_key: Vec<Vec<u8>> = potential_key
-            .into_iter()
-            .map(|key| key.into_iter().collect())
-            .collect();
-        let mut potential_key = PotentialKey::new(potential_key);
+    /// Fails if the contents are empty or no character of the alphabet can be encoded.
+    pub fn crack(&self, contents: &[u8], key_length: NonZeroUsize) -> Result<PotentialKey, Error> {
+        self.crack_with_progress(contents, key_length, &mut |_, _| true)
+    }
 
-        for word in &self.words {
-            for index in 0..(contents.len() - word.len()) {
-                let mut possible = true;
+    /// Crack the given contents with the given key length, reporting progress.
+    /// Fails with `Error::Cancelled` if cracking was cancelled.
+    pub fn crack_with_progress(
+        &self,
+        contents: &[u8],
+        key_length: NonZeroUsize,
+        progress: CrackProgress<'_>,
+    ) -> Result<PotentialKey, Error> {
+        self.check_input(&[contents])?;
+        let mut potential_key = self
+            .xor_attack(contents, key_length, progress)
+            .ok_or(Error::Cancelled)?;
+        self.sequence_attack(&mut potential_key, &[contents], progress)
+            .ok_or(Error::Cancelled)?;
+        let mut potential_key = self.rank(potential_key, &[contents], key_length.get());
+        self.match_words(&mut potential_key, &[contents], key_length.get(), progress)
+            .ok_or(Error::Cancelled)?;
+        Ok(potential_key)
+    }
 
-                for (i, byte) in word.iter().zip(&contents[index..]).enumerate() {
-                    if !potential_key.is_possible((index + i) % key_length, *byte.0 ^ *byte.1) {
-                        possible = false;
-                        break;
+    /// Crack the given ciphertexts encrypted with the same key.
+    /// Returned key covers the longest ciphertext.
+    pub fn crack_many(&self, ciphertexts: &[&[u8]]) -> Result<PotentialKey, Error> {
+        self.crack_many_with_progress(ciphertexts, &mut |_, _| true)
+    }
+
+    /// Crack the given ciphertexts encrypted with the same key, reporting progress.
+    /// Fails with `Error::Cancelled` if cracking was cancelled.
+    pub fn crack_many_with_progress(
+        &self,
+        ciphertexts: &[&[u8]],
+        progress: CrackProgress<'_>,
+    ) -> Result<PotentialKey, Error> {
+        self.check_input(ciphertexts)?;
+        let key_length = ciphertexts.iter().map(|c| c.len()).max().unwrap_or(0);
+        let mut potential_key = Vec::with_capacity(key_length);
+        for batch in parallel::batches(0..key_length) {
+            if !progress(
+                CrackPhase::XorAttack,
+                batch.start as f32 / key_length as f32,
+            ) {
+                return Err(Error::Cancelled);
+            }
+            potential_key.extend(parallel::map(batch, |position| {
+                let column: Vec<u8> = ciphertexts
+                    .iter()
+                    .filter_map(|ciphertext| ciphertext.get(position).copied())
+                    .collect();
+                self.column_attack(&column)
+            }));
+        }
+        self.sequence_attack(&mut potential_key, ciphertexts, progress)
+            .ok_or(Error::Cancelled)?;
+
+        let mut potential_key = self.rank(potential_key, ciphertexts, key_length);
+        self.match_words(&mut potential_key, ciphertexts, key_length, progress)
+            .ok_or(Error::Cancelled)?;
+        Ok(potential_key)
+    }
+
+    /// Check there is something to crack and an alphabet to crack it with.
+    fn check_input(&self, texts: &[&[u8]]) -> Result<(), Error> {
+        if texts.iter().all(|text| text.is_empty()) {
+            Err(Error::EmptyInput)
+        } else if self.alphabet.is_empty() {
+            Err(Error::EmptyAlphabet)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Sort candidates for every key position by log-likelihood of the plaintext they produce.
+    /// Score is the average of unigram log probabilities in the column and bigram log
+    /// probabilities with neighbours decrypted by their best unigram candidates.
+    fn rank(&self, candidates: Vec<ByteSet>, texts: &[&[u8]], key_length: usize) -> PotentialKey {
+        let mut columns: Vec<Vec<ColumnByte>> = (0..key_length).map(|_| Vec::new()).collect();
+        for text in texts {
+            for (index, byte) in text.iter().enumerate() {
+                columns[index % key_length].push(ColumnByte {
+                    previous: index.checked_sub(1).map(|previous| text[previous]),
+                    byte: *byte,
+                    next: text.get(index + 1).copied(),
+                });
+            }
+        }
+
+        let best: Vec<Option<u8>> = parallel::map(0..key_length, |position| {
+            candidates[position]
+                .iter()
+                .map(|key| {
+                    let score: f64 = columns[position]
+                        .iter()
+                        .map(|value| self.model.unigram(value.byte ^ key))
+                        .sum();
+                    (key, score)
+                })
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(key, _)| key)
+        });
+
+        let key = parallel::map(0..key_length, |position| {
+            let column = &columns[position];
+            let previous_key = best[(position + key_length - 1) % key_length];
+            let next_key = best[(position + 1) % key_length];
+
+            let mut scored: Vec<(u8, f64)> = candidates[position]
+                .iter()
+                .map(|key| {
+                    let mut score = 0.0;
+                    for value in column {
+                        let byte = value.byte ^ key;
+                        score += self.model.unigram(byte);
+                        if let (Some(previous), Some(previous_key)) = (value.previous, previous_key)
+                        {
+                            score += self.model.bigram(previous ^ previous_key, byte);
+                        }
+                        if let (Some(next), Some(next_key)) = (value.next, next_key) {
+                            score += self.model.bigram(byte, next ^ next_key);
+                        }
                     }
-                }
+                    (key, score / column.len().max(1) as f64)
+                })
+                .collect();
 
-                if !possible {
-                    continue;
-                }
+            scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+            scored
+        });
+
+        PotentialKey::with_scores(key)
+    }
+
+    /// Vote for key values implied by every place in the texts where a word fits,
+    /// then set every position with votes to the value with the most votes.
+    /// All words are found in a single pass over each text, so the order of words does not
+    /// matter, only their weights.
+    /// Returns none if cancelled.
+    fn match_words(
+        &self,
+        potential_key: &mut PotentialKey,
+        texts: &[&[u8]],
+        key_length: usize,
+        progress: CrackProgress<'_>,
+    ) -> Option<()> {
+        let total = texts.iter().map(|text| text.len()).sum::<usize>() as f32;
+        let mut searched = 0;
+
+        for contents in texts {
+            // Words are not matched at the last byte, so no word ends there.
+            let searched_length = contents.len().saturating_sub(1);
+            let chunks = (searched_length + WORD_MATCHING_CHUNK - 1) / WORD_MATCHING_CHUNK;
 
-                for (i, byte) in word.iter().zip(&contents[index..]).enumerate() {
-                    potential_key.set_value((index + i) % key_length, *byte.0 ^ *byte.1);
+            for batch in parallel::batches(0..chunks) {
+                let offset = batch.start * WORD_MATCHING_CHUNK;
+                if !progress(CrackPhase::WordMatching, (searched + offset) as f32 / total) {
+                    return None;
+                }
+                let found = parallel::map(batch, |chunk| {
+                    let chunk = chunk * WORD_MATCHING_CHUNK..(chunk + 1) * WORD_MATCHING_CHUNK;
+                    self.search_chunk(potential_key, contents, searched_length, chunk, key_length)
+                });
+                for (word, index) in found.into_iter().flatten() {
+                    let word_bytes = self.words[word].iter().zip(&contents[index..]);
+                    for (i, (byte, encrypted)) in word_bytes.enumerate() {
+                        potential_key.add_vote(
+                            (index + i) % key_length,
+                            byte ^ encrypted,
+                            self.weights[word],
+                        );
+                    }
                 }
             }
+            searched += contents.len();
         }
 
-        potential_key
+        potential_key.choose_by_votes();
+        Some(())
     }
 
-    fn xor_attack(&self, contents: &[u8], key_length: NonZeroUsize) -> Vec<HashSet<u8>> {
-        let key_length = key_length.get();
-        let mut remaining_bytes = key_length;
-        let mut key: Vec<HashSet<u8>> = {
-            let bytes = (0..=255u8).collect();
-            vec![bytes; key_length]
+    /// Find words fitting the key and their neighbours that end in a chunk of the text,
+    /// in order of their end. The search starts early enough to find the longest word.
+    /// Returns the word index and start offset of every match.
+    fn search_chunk(
+        &self,
+        potential_key: &PotentialKey,
+        contents: &[u8],
+        searched_length: usize,
+        chunk: Range<usize>,
+        key_length: usize,
+    ) -> Vec<(usize, usize)> {
+        let start = chunk.start;
+        let end = chunk.end.min(searched_length);
+        let first = start.saturating_sub(self.matcher.longest().saturating_sub(1));
+        let mut search = self.matcher.search_at(first);
+        let mut found = Vec::new();
+
+        for (offset, byte) in contents.iter().enumerate().take(end).skip(first) {
+            // Matching only changes current values, possible values stay the same.
+            let possible = potential_key.get_possible_set(offset % key_length);
+            search.push(
+                |value| possible.contains(value ^ byte),
+                |word, index| {
+                    if offset >= start
+                        && self.fits_neighbours(
+                            potential_key,
+                            contents,
+                            index,
+                            &self.words[word],
+                            key_length,
+                        )
+                    {
+                        found.push((word, index));
+                    }
+                },
+            );
+        }
+
+        found
+    }
+
+    /// Check that a word placed at the index keeps multibyte characters around it whole.
+    /// Bytes next to the word are decrypted with any possible value of their key position,
+    /// or with the value the word itself implies if the key wraps inside the word.
+    fn fits_neighbours(
+        &self,
+        potential_key: &PotentialKey,
+        contents: &[u8],
+        index: usize,
+        word: &[u8],
+        key_length: usize,
+    ) -> bool {
+        let (Some(transitions), Some(first), Some(last)) =
+            (&self.transitions, word.first(), word.last())
+        else {
+            return true;
+        };
+
+        let neighbour_fits = |offset: usize, check: &dyn Fn(u8) -> bool| {
+            let position = offset % key_length;
+            let implied = word
+                .iter()
+                .zip(&contents[index..])
+                .enumerate()
+                .find(|(i, _)| (index + i) % key_length == position)
+                .map(|(_, (byte, encrypted))| byte ^ encrypted);
+            let possibilities = potential_key.get_possibilities(position);
+            match implied {
+                Some(value) => check(contents[offset] ^ value),
+                // A position without possibilities is an error, it can not rule the word out.
+                None if possibilities.is_empty() => true,
+                None => possibilities
+                    .iter()
+                    .any(|value| check(contents[offset] ^ value)),
+            }
         };
 
-        for iteration in 0..(contents.len() / key_length) {
-            let chunk = &contents[(iteration * key_length)..((iteration + 1) * key_length)];
-            for (index, byte) in chunk.iter().enumerate() {
-                let position = index % key_length;
+        let before = match index.checked_sub(1) {
+            Some(offset) => neighbour_fits(offset, &|byte| transitions.is_allowed(byte, *first)),
+            None => transitions.is_start(*first),
+        };
+        let after_offset = index + word.len();
+        let after = if after_offset < contents.len() {
+            neighbour_fits(after_offset, &|byte| transitions.is_allowed(*last, byte))
+        } else {
+            transitions.is_end(*last)
+        };
 
-                if key[position].len() <= 1 {
-                    continue;
+        before && after
+    }
+
+    /// Find key bytes possible for a column of bytes encrypted with the same key byte,
+    /// a key byte is kept only if it decrypts every byte of the column to the alphabet.
+    /// This implies every pairwise constraint: if `a ^ k` and `b ^ k` are in the alphabet,
+    /// `a ^ b` is the XOR of two alphabet bytes.
+    fn column_attack(&self, column: &[u8]) -> ByteSet {
+        let bytes: ByteSet = column.iter().collect();
+        bytes.iter().fold(ByteSet::FULL, |key, byte| {
+            key & self.key_bytes[byte as usize]
+        })
+    }
+
+    /// Remove key bytes that break multibyte characters, so every decrypted byte can follow
+    /// the previous one. Neighbouring key positions are narrowed until nothing changes.
+    /// Returns none if cancelled.
+    fn sequence_attack(
+        &self,
+        key: &mut [ByteSet],
+        texts: &[&[u8]],
+        progress: CrackProgress<'_>,
+    ) -> Option<()> {
+        let (Some(transitions), key_length) = (&self.transitions, key.len()) else {
+            return Some(());
+        };
+        if key_length == 0 {
+            return Some(());
+        }
+
+        for text in texts {
+            if let Some(first) = text.first() {
+                key[0].retain(|value| transitions.is_start(first ^ value));
+            }
+            if let Some(last) = text.last() {
+                key[(text.len() - 1) % key_length].retain(|value| transitions.is_end(last ^ value));
+            }
+        }
+
+        // Ciphertext byte pairs starting at each key position.
+        let mut pairs: Vec<HashSet<(u8, u8)>> = vec![HashSet::default(); key_length];
+        for text in texts {
+            for (index, pair) in text.windows(2).enumerate() {
+                pairs[index % key_length].insert((pair[0], pair[1]));
+            }
+        }
+        let fits = |position: usize, value: u8, next_value: u8| {
+            pairs[position]
+                .iter()
+                .all(|(byte, next)| transitions.is_allowed(byte ^ value, next ^ next_value))
+        };
+
+        let mut changed = true;
+        while changed {
+            if !progress(CrackPhase::XorAttack, 1.0) {
+                return None;
+            }
+            changed = false;
+
+            for position in 0..key_length {
+                let next_position = (position + 1) % key_length;
+                let sizes = (key[position].len(), key[next_position].len());
+
+                if position == next_position {
+                    key[position].retain(|value| fits(position, value, value));
+                } else if !key[position].is_empty() && !key[next_position].is_empty() {
+                    // An empty neighbour is already an error, it must not spread.
+                    let next_values = key[next_position];
+                    key[position].retain(|value| {
+                        next_values
+                            .iter()
+                            .any(|next_value| fits(position, value, next_value))
+                    });
+                    let values = key[position];
+                    key[next_position].retain(|next_value| {
+                        values.iter().any(|value| fits(position, value, next_value))
+                    });
                 }
 
-                let value = *byte ^ chunk[position];
-                let possibilities = self.combinations[&value]
+                changed |= sizes != (key[position].len(), key[next_position].len());
+            }
+        }
+
+        Some(())
+    }
+
+    /// Find key bytes possible for every key position, using all bytes of its column
+    /// including the last incomplete row.
+    /// Returns none if cancelled.
+    fn xor_attack(
+        &self,
+        contents: &[u8],
+        key_length: NonZeroUsize,
+        progress: CrackProgress<'_>,
+    ) -> Option<Vec<ByteSet>> {
+        let key_length = key_length.get();
+        let mut key = Vec::with_capacity(key_length);
+
+        for batch in parallel::batches(0..key_length) {
+            if !progress(
+                CrackPhase::XorAttack,
+                batch.start as f32 / key_length as f32,
+            ) {
+                return None;
+            }
+            key.extend(parallel::map(batch, |position| {
+                let column: Vec<u8> = contents
                     .iter()
-                    .map(|value| chunk[position] ^ *value)
+                    .skip(position)
+                    .step_by(key_length)
+                    .copied()
                     .collect();
+                self.column_attack(&column)
+            }));
+        }
+
+        Some(key)
+    }
+}
+
+/// Split words from their votes. A vote grows with the cube of the word length, as a long word
+/// is far less likely to fit by chance, and the most frequent words count up to twice as much
+/// as the rarest ones, so short words like "i" or "w" decide only positions no long word covers.
+fn word_weights(words: Vec<(Vec<u8>, f64)>) -> (Vec<Vec<u8>>, Vec<f64>) {
+    let max_frequency = words
+        .iter()
+        .map(|(_, frequency)| *frequency)
+        .fold(0.0, f64::max);
+    words
+        .into_iter()
+        .map(|(word, frequency)| {
+            let relative = if max_frequency > 0.0 {
+                (frequency / max_frequency).max(0.0)
+            } else {
+                0.0
+            };
+            let weight = (word.len() as f64).powi(3) * (1.0 + relative);
+            (word, weight)
+        })
+        .unzip()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_random::Random;
+    use crate::Cipher;
+
+    /// Previous XOR attack, kept as the reference the column attack is checked against.
+    /// It intersected every position with `byte ^ alphabet` for bytes of whole rows only,
+    /// and stopped once every position had at most one candidate.
+    fn previous_xor_attack(
+        alphabet: ByteSet,
+        contents: &[u8],
+        key_length: usize,
+    ) -> Vec<HashSet<u8>> {
+        let mut key: Vec<HashSet<u8>> = vec![(0..=255u8).collect(); key_length];
+        let mut remaining_bytes = key_length;
 
+        for chunk in contents.chunks_exact(key_length) {
+            for (position, byte) in chunk.iter().enumerate() {
+                if key[position].len() <= 1 {
+                    continue;
+                }
+                let possibilities = alphabet.iter().map(|value| byte ^ value).collect();
                 key[position] = key[position]
                     .intersection(&possibilities)
                     .copied()
@@ -98,7 +530,6 @@ impl Cracker {
                 if key[position].len() <= 1 {
                     remaining_bytes -= 1;
                 }
-
                 if remaining_bytes == 0 {
                     return key;
                 }
@@ -106,4 +537,302 @@ impl Cracker {
         }
         key
     }
+
+    /// Previous word matching, kept as the reference the single pass is checked against.
+    /// It scanned the text for every word separately and returns placements fitting the key.
+    fn previous_placements(
+        cracker: &Cracker,
+        potential_key: &PotentialKey,
+        contents: &[u8],
+        key_length: usize,
+    ) -> Vec<(usize, usize)> {
+        let mut placements = Vec::new();
+        for (word_index, word) in cracker.words.iter().enumerate() {
+            for index in 0..contents.len().saturating_sub(word.len()) {
+                let possible = word.iter().zip(&contents[index..]).enumerate().all(
+                    |(i, (byte, encrypted))| {
+                        potential_key.is_possible((index + i) % key_length, byte ^ encrypted)
+                    },
+                );
+                if possible
+                    && cracker.fits_neighbours(potential_key, contents, index, word, key_length)
+                {
+                    placements.push((word_index, index));
+                }
+            }
+        }
+        placements
+    }
+
+    /// The new candidates of a position are the key bytes decrypting every byte of its column
+    /// to the alphabet. The previous candidates applied the same condition to a subset of the
+    /// column, so the new set is a subset of the previous one. The true key byte decrypts every
+    /// byte to plaintext, which is made of the alphabet, so it is never removed.
+    #[test]
+    fn column_attack_narrows_previous_candidates_and_keeps_key() {
+        let language = LanguagePack::polish();
+        let mut random = Random(0x9E37_79B9_7F4A_7C15);
+
+        for encoding in [TextEncoding::WINDOWS1250, TextEncoding::UTF8] {
+            let cracker = Cracker::with_language(&encoding, &language);
+            let characters = Alphabet::literal(&language.alphabet).sequences(&encoding);
+
+            for _ in 0..20 {
+                let mut plaintext = Vec::new();
+                let characters_count = 1 + random.below(600);
+                for _ in 0..characters_count {
+                    plaintext.extend(&characters[random.below(characters.len())]);
+                }
+                let key_length = 1 + random.below(plaintext.len().min(300));
+                let key: Vec<u8> = (0..key_length).map(|_| random.next() as u8).collect();
+                let contents = Cipher::new(key.clone())
+                    .expect("key is not empty")
+                    .encrypt(&plaintext);
+
+                let key_length = NonZeroUsize::new(key_length).expect("key length is positive");
+                let candidates = cracker
+                    .xor_attack(&contents, key_length, &mut |_, _| true)
+                    .expect("attack is never cancelled");
+                let previous = previous_xor_attack(cracker.alphabet, &contents, key_length.get());
+
+                for (position, value) in key.iter().enumerate() {
+                    assert!(candidates[position]
+                        .iter()
+                        .all(|candidate| previous[position].contains(&candidate)));
+                    assert!(candidates[position].contains(*value));
+                }
+            }
+        }
+    }
+
+    /// Ciphertext of random dictionary words encrypted with a random key, in several texts.
+    fn random_texts(random: &mut Random, cracker: &Cracker, key_length: usize) -> Vec<Vec<u8>> {
+        let key: Vec<u8> = (0..key_length).map(|_| random.next() as u8).collect();
+        let cipher = Cipher::new(key).expect("key is not empty");
+        (0..1 + random.below(3))
+            .map(|_| {
+                let mut plaintext = Vec::new();
+                for _ in 0..random.below(300) {
+                    plaintext.extend(&cracker.words[random.below(cracker.words.len())]);
+                    plaintext.push(b' ');
+                }
+                cipher.encrypt(&plaintext)
+            })
+            .collect()
+    }
+
+    /// Searching all words in one pass over chunks of the text finds the same placements
+    /// as scanning the text for every word, also for keys shorter than the words.
+    #[test]
+    fn search_finds_previous_placements() {
+        let language = LanguagePack::polish();
+        let mut random = Random(0x2545_F491_4F6C_DD1D);
+
+        for encoding in [TextEncoding::WINDOWS1250, TextEncoding::UTF8] {
+            let cracker = Cracker::with_language(&encoding, &language);
+
+            for _ in 0..20 {
+                let key_length = 1 + random.below(200);
+                let texts = random_texts(&mut random, &cracker, key_length);
+                let texts: Vec<&[u8]> = texts.iter().map(|text| text.as_slice()).collect();
+
+                let mut candidates: Vec<ByteSet> = (0..key_length)
+                    .map(|position| {
+                        let column: Vec<u8> = texts
+                            .iter()
+                            .flat_map(|text| text.iter().skip(position).step_by(key_length))
+                            .copied()
+                            .collect();
+                        cracker.column_attack(&column)
+                    })
+                    .collect();
+                cracker
+                    .sequence_attack(&mut candidates, &texts, &mut |_, _| true)
+                    .expect("attack is never cancelled");
+                let potential_key = cracker.rank(candidates, &texts, key_length);
+
+                for contents in texts {
+                    let previous =
+                        previous_placements(&cracker, &potential_key, contents, key_length);
+                    let searched_length = contents.len().saturating_sub(1);
+                    // Small chunks check words crossing their boundaries.
+                    for chunk_size in [7, 64, WORD_MATCHING_CHUNK] {
+                        let mut placements: Vec<(usize, usize)> = (0..searched_length)
+                            .step_by(chunk_size)
+                            .flat_map(|start| {
+                                cracker.search_chunk(
+                                    &potential_key,
+                                    contents,
+                                    searched_length,
+                                    start..start + chunk_size,
+                                    key_length,
+                                )
+                            })
+                            .collect();
+                        placements.sort_unstable();
+
+                        assert_eq!(placements, previous, "chunks of {chunk_size} bytes");
+                    }
+                }
+            }
+        }
+    }
+
+    /// Votes make the cracked key independent of the order of words with equal frequencies.
+    #[test]
+    fn word_order_does_not_change_key() {
+        let mut language = LanguagePack::polish();
+        for (_, frequency) in &mut language.words {
+            *frequency = 1.0;
+        }
+        let mut reversed = language.clone();
+        reversed.words.reverse();
+        let mut random = Random(0x9E37_79B9_7F4A_7C15);
+
+        for encoding in [TextEncoding::WINDOWS1250, TextEncoding::UTF8] {
+            let cracker = Cracker::with_language(&encoding, &language);
+            let reversed_cracker = Cracker::with_language(&encoding, &reversed);
+            let mut words = reversed_cracker.words.clone();
+            assert_ne!(cracker.words, words);
+            words.reverse();
+            assert_eq!(cracker.words, words);
+
+            for _ in 0..10 {
+                let key_length = 1 + random.below(100);
+                let texts = random_texts(&mut random, &cracker, key_length);
+                let Some(contents) = texts.iter().find(|text| !text.is_empty()) else {
+                    continue;
+                };
+                let key_length = NonZeroUsize::new(key_length).expect("key length is positive");
+
+                let key = cracker
+                    .crack(contents, key_length)
+                    .expect("text is not empty");
+                let reversed_key = reversed_cracker
+                    .crack(contents, key_length)
+                    .expect("text is not empty");
+                assert_eq!(key.get_current_key(), reversed_key.get_current_key());
+            }
+        }
+    }
+
+    /// Messages encrypted with one keystream keep the true key byte at every position,
+    /// and the key covers the longest message.
+    #[test]
+    fn crack_many_keeps_keystream() {
+        let language = LanguagePack::polish();
+        let mut random = Random(0x2545_F491_4F6C_DD1D);
+
+        for encoding in [TextEncoding::WINDOWS1250, TextEncoding::UTF8] {
+            let cracker = Cracker::with_language(&encoding, &language);
+
+            for _ in 0..5 {
+                let plaintexts: Vec<Vec<u8>> = (0..2 + random.below(6))
+                    .map(|_| {
+                        let mut plaintext = Vec::new();
+                        for _ in 0..1 + random.below(60) {
+                            plaintext.extend(&cracker.words[random.below(cracker.words.len())]);
+                            plaintext.push(b' ');
+                        }
+                        plaintext
+                    })
+                    .collect();
+                let longest = plaintexts.iter().map(Vec::len).max().unwrap_or(0);
+                let keystream: Vec<u8> = (0..longest).map(|_| random.next() as u8).collect();
+                let cipher = Cipher::new(keystream.clone()).expect("keystream is not empty");
+                let ciphertexts: Vec<Vec<u8>> = plaintexts
+                    .iter()
+                    .map(|plaintext| cipher.encrypt(plaintext))
+                    .collect();
+                let ciphertexts: Vec<&[u8]> = ciphertexts.iter().map(Vec::as_slice).collect();
+
+                let key = cracker
+                    .crack_many(&ciphertexts)
+                    .expect("messages are not empty");
+
+                assert_eq!(key.len(), longest);
+                for (position, value) in keystream.iter().enumerate() {
+                    assert!(key.is_possible(position, *value), "position {position}");
+                }
+            }
+        }
+    }
+
+    /// A key value decrypting the lead bytes of two-byte letters to a single byte character,
+    /// or their continuation bytes to ones, splits the letters and is dropped by its neighbour.
+    #[test]
+    fn sequence_attack_keeps_two_byte_letters_whole() {
+        let cracker = Cracker::new(&TextEncoding::UTF8);
+        // Lead bytes are at even offsets and continuation bytes at odd ones.
+        let plaintext = "ąęąęę".repeat(8).into_bytes();
+        let key = [0x3C, 0xA7];
+        let contents = Cipher::new(key.to_vec())
+            .expect("key is not empty")
+            .encrypt(&plaintext);
+        let columns: Vec<Vec<u8>> = (0..2)
+            .map(|position| contents.iter().skip(position).step_by(2).copied().collect())
+            .collect();
+
+        // Both values decrypt their whole column to the alphabet: leads to `a`,
+        // continuations of `ą` and `ę` to `q` and `m`.
+        let splitting = [contents[0] ^ b'a', contents[1] ^ b'q'];
+        for position in 0..2 {
+            let mut candidates: Vec<ByteSet> = key
+                .iter()
+                .map(|value| [*value].into_iter().collect())
+                .collect();
+            candidates[position].insert(splitting[position]);
+            assert!(candidates[position].is_subset(&cracker.column_attack(&columns[position])));
+
+            cracker
+                .sequence_attack(&mut candidates, &[&contents], &mut |_, _| true)
+                .expect("attack is never cancelled");
+
+            for (position, value) in key.iter().enumerate() {
+                assert_eq!(candidates[position], [*value].into_iter().collect());
+            }
+        }
+    }
+
+    #[test]
+    fn empty_input_is_rejected() {
+        let cracker = Cracker::new(&TextEncoding::UTF8);
+        let length = NonZeroUsize::new(3).unwrap();
+
+        assert_eq!(cracker.crack(&[], length).err(), Some(Error::EmptyInput));
+        assert_eq!(cracker.crack_many(&[]).err(), Some(Error::EmptyInput));
+        assert_eq!(
+            cracker.crack_many(&[&[], &[]]).err(),
+            Some(Error::EmptyInput)
+        );
+    }
+
+    /// Input shorter than the longest word, or than the key, still gives a key of the
+    /// requested length keeping the true key byte at every encrypted position.
+    #[test]
+    fn input_shorter_than_longest_word_is_cracked() {
+        let language = LanguagePack::polish();
+        let cracker = Cracker::with_language(&TextEncoding::WINDOWS1250, &language);
+        let longest = cracker.words.iter().map(Vec::len).max().unwrap_or(0);
+        let keystream = [0x51, 0x0E, 0xC3, 0x7A, 0x29];
+        let cipher = Cipher::new(keystream.to_vec()).expect("key is not empty");
+
+        for plaintext in [&b"a"[..], b"ma", b"kot"] {
+            assert!(plaintext.len() < longest);
+            let contents = cipher.encrypt(plaintext);
+
+            for key_length in [1, plaintext.len(), keystream.len()] {
+                let key = cracker
+                    .crack(&contents, NonZeroUsize::new(key_length).unwrap())
+                    .expect("input is not empty");
+
+                assert_eq!(key.len(), key_length);
+                if key_length >= plaintext.len() {
+                    for (position, value) in keystream.iter().take(plaintext.len()).enumerate() {
+                        assert!(key.is_possible(position, *value));
+                    }
+                }
+            }
+        }
+    }
 }
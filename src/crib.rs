@@ -0,0 +1,108 @@
+use crate::{Error, PotentialKey};
+
+/// A placement of a known plaintext fragment in the ciphertext.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CribPlacement {
+    /// Offset of the crib in the ciphertext.
+    pub offset: usize,
+    /// Key bytes implied by the crib, starting at key position of the offset.
+    pub key: Vec<u8>,
+    /// Average score of the implied key bytes, higher is better.
+    pub score: f64,
+}
+
+impl CribPlacement {
+    /// Key position of the first implied key byte.
+    pub fn key_position(&self, key_length: usize) -> usize {
+        self.offset % key_length
+    }
+
+    /// Decrypt the same key positions elsewhere in the contents with the implied key bytes.
+    /// Returns offsets with decrypted bytes, the placement itself is skipped.
+    pub fn echoes(&self, contents: &[u8], key_length: usize) -> Vec<(usize, Vec<u8>)> {
+        let length = self.key.len().min(key_length);
+        let mut echoes = Vec::new();
+        let mut offset = self.key_position(key_length);
+
+        while offset < contents.len() {
+            if offset != self.offset {
+                let bytes = contents[offset..]
+                    .iter()
+                    .zip(&self.key[..length])
+                    .map(|(byte, key)| byte ^ key)
+                    .collect();
+                echoes.push((offset, bytes));
+            }
+            offset += key_length;
+        }
+
+        echoes
+    }
+
+    /// Set implied key bytes as current values of the key.
+    /// The placement may be stale after the key changed, so every byte is checked first and
+    /// the key is left unchanged if any of them is not possible or two disagree on a position.
+    pub fn apply(&self, key: &mut PotentialKey) -> Result<(), Error> {
+        let key_length = key.len();
+        if key_length == 0 {
+            return Err(Error::EmptyKey);
+        }
+
+        let values: Vec<(usize, u8)> = self
+            .key
+            .iter()
+            .enumerate()
+            .map(|(index, value)| ((self.offset + index) % key_length, *value))
+            .collect();
+        for (index, (position, value)) in values.iter().enumerate() {
+            let disagrees = index >= key_length && values[index - key_length].1 != *value;
+            if disagrees || !key.is_possible(*position, *value) {
+                return Err(Error::ImpossibleValue {
+                    index: *position,
+                    value: *value,
+                });
+            }
+        }
+
+        key.set_values(&values[..values.len().min(key_length)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply_leaves_key_unchanged_on_failure() {
+        let mut key = PotentialKey::new(vec![vec![1, 2], vec![3, 4], vec![5, 6]]);
+        let before = key.get_current_key();
+
+        let impossible = CribPlacement {
+            offset: 1,
+            key: vec![4, 7],
+            score: 0.0,
+        };
+        assert!(impossible.apply(&mut key).is_err());
+        assert_eq!(key.get_current_key(), before);
+        assert!(key.history().is_empty());
+
+        let disagreeing = CribPlacement {
+            offset: 0,
+            key: vec![2, 4, 6, 1],
+            score: 0.0,
+        };
+        assert!(disagreeing.apply(&mut key).is_err());
+        assert_eq!(key.get_current_key(), before);
+
+        let fitting = CribPlacement {
+            offset: 2,
+            key: vec![6, 2, 4, 6],
+            score: 0.0,
+        };
+        assert_eq!(fitting.apply(&mut key), Ok(()));
+        assert_eq!(key.get_current_key(), vec![2, 4, 6]);
+        assert_eq!(key.history().len(), 1);
+        assert!(key.undo());
+        assert_eq!(key.get_current_key(), before);
+    }
+}
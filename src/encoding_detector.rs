@@ -0,0 +1,221 @@
+use crate::{
+    Alphabet, Cipher, CrackPhase, CrackProgress, Cracker, Error, LanguagePack, PotentialKey,
+    TextEncoding, WordMatcher,
+};
+use std::num::NonZeroUsize;
+
+/// Shortest dictionary word counted when scoring, shorter words match by chance too often.
+const MIN_WORD_LENGTH: usize = 3;
+
+/// Share of the progress of one encoding spent cracking, the rest is scoring.
+const CRACKING_SHARE: f32 = 0.9;
+
+/// Plaintext bytes scored between progress reports.
+const SCORING_BATCH: usize = 1 << 16;
+
+/// An encoding with the key cracked under it and its score.
+#[derive(Clone)]
+pub struct EncodingCandidate {
+    pub encoding: TextEncoding,
+    /// Score in range from -1 to 2, higher is better.
+    pub score: f64,
+    pub key: PotentialKey,
+}
+
+/// A detector of the plaintext encoding, cracking the ciphertext under every encoding.
+pub struct EncodingDetector {
+    language: LanguagePack,
+    alphabet: Option<Alphabet>,
+}
+
+impl EncodingDetector {
+    /// Create a new detector for texts in the given language.
+    /// The alphabet replaces the language alphabet if given.
+    pub fn new(language: LanguagePack, alphabet: Option<Alphabet>) -> Self {
+        Self { language, alphabet }
+    }
+
+    /// Rank encodings for the given contents, the best candidate comes first.
+    /// Encodings that can not encode the alphabet are left out.
+    pub fn detect(
+        &self,
+        contents: &[u8],
+        key_length: NonZeroUsize,
+    ) -> Result<Vec<EncodingCandidate>, Error> {
+        self.detect_with_progress(contents, key_length, &mut |_, _| true)
+    }
+
+    /// Rank encodings for the given contents, reporting progress over all encodings.
+    /// Fails with `Error::Cancelled` if detection was cancelled.
+    pub fn detect_with_progress(
+        &self,
+        contents: &[u8],
+        key_length: NonZeroUsize,
+        progress: CrackProgress<'_>,
+    ) -> Result<Vec<EncodingCandidate>, Error> {
+        let count = TextEncoding::ALL.len() as f32;
+        let mut candidates = Vec::with_capacity(TextEncoding::ALL.len());
+
+        for (index, encoding) in TextEncoding::ALL.into_iter().enumerate() {
+            let cracker = match &self.alphabet {
+                Some(alphabet) => Cracker::with_alphabet(&encoding, &self.language, alphabet),
+                None => Cracker::with_language(&encoding, &self.language),
+            };
+            let result = cracker.crack_with_progress(contents, key_length, &mut |phase, value| {
+                let offset = match phase {
+                    CrackPhase::WordMatching => 0.5,
+                    _ => 0.0,
+                };
+                progress(
+                    CrackPhase::EncodingDetection,
+                    (index as f32 + (offset + value / 2.0) * CRACKING_SHARE) / count,
+                )
+            });
+            let key = match result {
+                Err(Error::EmptyAlphabet) => continue,
+                result => result?,
+            };
+
+            let score = self
+                .score(&encoding, &key, contents, &mut |value| {
+                    progress(
+                        CrackPhase::EncodingDetection,
+                        (index as f32 + CRACKING_SHARE + value * (1.0 - CRACKING_SHARE)) / count,
+                    )
+                })
+                .ok_or(Error::Cancelled)?;
+            candidates.push(EncodingCandidate {
+                score,
+                encoding,
+                key,
+            });
+        }
+
+        if candidates.is_empty() {
+            return Err(Error::EmptyAlphabet);
+        }
+        // Stable sort keeps the order of `TextEncoding::ALL` for equal scores.
+        candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+        Ok(candidates)
+    }
+
+    /// Get the most probable encoding with its key.
+    pub fn best(
+        &self,
+        contents: &[u8],
+        key_length: NonZeroUsize,
+    ) -> Result<EncodingCandidate, Error> {
+        let candidates = self.detect(contents, key_length)?;
+        candidates.into_iter().next().ok_or(Error::EmptyAlphabet)
+    }
+
+    /// Fraction of decoded key positions, minus fraction of positions in error,
+    /// plus fraction of the plaintext covered by dictionary words.
+    /// Returns none if cancelled.
+    fn score(
+        &self,
+        encoding: &TextEncoding,
+        key: &PotentialKey,
+        contents: &[u8],
+        progress: &mut dyn FnMut(f32) -> bool,
+    ) -> Option<f64> {
+        let Ok(cipher) = Cipher::new(key.get_current_key()) else {
+            return Some(0.0);
+        };
+        if contents.is_empty() {
+            return Some(0.0);
+        }
+
+        let positions = key.len() as f64;
+        let decoded = (0..key.len())
+            .filter(|index| key.is_decoded(*index))
+            .count() as f64;
+        let errors = (0..key.len()).filter(|index| key.is_error(*index)).count() as f64;
+
+        let plaintext = cipher.decrypt(contents);
+        let words: Vec<Vec<u8>> = self
+            .language
+            .words(encoding)
+            .into_iter()
+            .filter(|word| word.len() >= MIN_WORD_LENGTH)
+            .collect();
+        let matcher = WordMatcher::new(&words);
+        let mut search = matcher.search();
+        // Words found at an offset all end there, so they cover one range up to it
+        // and only its part after the already covered bytes is new.
+        let mut covered = 0;
+        let mut covered_end = 0;
+        for (offset, byte) in plaintext.iter().enumerate() {
+            let mut start = usize::MAX;
+            search.push(
+                |value| value == *byte,
+                |_, word_start| start = start.min(word_start),
+            );
+            if start <= offset {
+                covered += offset + 1 - start.max(covered_end);
+                covered_end = offset + 1;
+            }
+
+            let scored = offset + 1;
+            if (scored % SCORING_BATCH == 0 || scored == plaintext.len())
+                && !progress(scored as f32 / plaintext.len() as f32)
+            {
+                return None;
+            }
+        }
+
+        Some((decoded - errors) / positions + covered as f64 / plaintext.len() as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn score_counts_covered_bytes_once() {
+        let detector = EncodingDetector::new(LanguagePack::polish(), None);
+        let encoding = TextEncoding::WINDOWS1250;
+        let contents = encoding
+            .encode("xxjest to jestem, przez tak bardzo...tak")
+            .unwrap();
+        let key = PotentialKey::new(vec![vec![0]]);
+
+        let mut covered = vec![false; contents.len()];
+        for word in detector.language.words(&encoding) {
+            if word.len() < MIN_WORD_LENGTH {
+                continue;
+            }
+            for (index, window) in contents.windows(word.len()).enumerate() {
+                if window == word.as_slice() {
+                    covered[index..index + word.len()].fill(true);
+                }
+            }
+        }
+        let covered = covered.iter().filter(|covered| **covered).count();
+        assert!(covered > 0);
+
+        let score = detector
+            .score(&encoding, &key, &contents, &mut |_| true)
+            .unwrap();
+        let decoded = if key.is_decoded(0) { 1.0 } else { 0.0 };
+        assert!((score - decoded - covered as f64 / contents.len() as f64).abs() < 1e-9);
+    }
+
+    #[test]
+    fn scoring_can_be_cancelled() {
+        let detector = EncodingDetector::new(LanguagePack::polish(), None);
+        let contents = Cipher::new(vec![7, 1, 200]).unwrap().encrypt(
+            &TextEncoding::UTF8
+                .encode(&"jest to tak ".repeat(20))
+                .unwrap(),
+        );
+        let scoring = CRACKING_SHARE / TextEncoding::ALL.len() as f32;
+        let result = detector.detect_with_progress(
+            &contents,
+            NonZeroUsize::new(3).unwrap(),
+            &mut |phase, value| phase != CrackPhase::EncodingDetection || value <= scoring,
+        );
+        assert!(matches!(result, Err(Error::Cancelled)));
+    }
+}
@@ -0,0 +1,48 @@
+use std::fmt::{Display, Formatter};
+
+/// Error of ciphers, crackers and potential keys.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// Cipher key contains no bytes.
+    EmptyKey,
+    /// There are no ciphertext bytes to crack.
+    EmptyInput,
+    /// No character of the alphabet can be encoded in the encoding.
+    EmptyAlphabet,
+    /// Progress callback asked to stop cracking.
+    Cancelled,
+    /// Key position is past the end of the key.
+    KeyPosition { index: usize, length: usize },
+    /// Value is not a candidate of the key position.
+    ImpossibleValue { index: usize, value: u8 },
+    /// Key position has no candidates, no value decrypts its bytes to the alphabet.
+    NoPossibleValue { index: usize },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::EmptyKey => write!(f, "Key is empty"),
+            Error::EmptyInput => write!(f, "Nothing to crack, the ciphertext is empty"),
+            Error::EmptyAlphabet => write!(f, "No character of the alphabet can be encoded"),
+            Error::Cancelled => write!(f, "Cracking was cancelled"),
+            Error::KeyPosition { index, length } => {
+                write!(
+                    f,
+                    "Key position {index} is out of range of key length {length}"
+                )
+            }
+            Error::ImpossibleValue { index, value } => {
+                write!(
+                    f,
+                    "Value {value:02X} is not possible at key position {index}"
+                )
+            }
+            Error::NoPossibleValue { index } => {
+                write!(f, "Key position {index} has no possible value")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,156 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// Format of key files.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeyFormat {
+    /// Key bytes as they are.
+    #[default]
+    Raw,
+    /// Two hexadecimal digits per byte.
+    Hex,
+    /// Standard base64 with padding.
+    Base64,
+}
+
+/// Error while reading a key.
+#[derive(Debug, PartialEq, Eq)]
+pub enum KeyFormatError {
+    /// Key contains no bytes.
+    Empty,
+    /// Key is not valid hexadecimal.
+    Hex,
+    /// Key is not valid base64.
+    Base64(base64::DecodeError),
+}
+
+impl KeyFormat {
+    pub const ALL: [KeyFormat; 3] = [KeyFormat::Raw, KeyFormat::Hex, KeyFormat::Base64];
+
+    /// Read a key, whitespace around and inside hex and base64 keys is ignored.
+    pub fn read(&self, contents: &[u8]) -> Result<Vec<u8>, KeyFormatError> {
+        let text: Vec<u8> = contents
+            .iter()
+            .copied()
+            .filter(|byte| !byte.is_ascii_whitespace())
+            .collect();
+
+        let key = match self {
+            KeyFormat::Raw => contents.to_vec(),
+            KeyFormat::Hex => {
+                if text.len() % 2 != 0 {
+                    return Err(KeyFormatError::Hex);
+                }
+                text.chunks(2)
+                    .map(|pair| Some(hex_digit(pair[0])? << 4 | hex_digit(pair[1])?))
+                    .collect::<Option<_>>()
+                    .ok_or(KeyFormatError::Hex)?
+            }
+            KeyFormat::Base64 => STANDARD.decode(text).map_err(KeyFormatError::Base64)?,
+        };
+
+        if key.is_empty() {
+            Err(KeyFormatError::Empty)
+        } else {
+            Ok(key)
+        }
+    }
+
+    /// Write a key, text formats end with a line break.
+    pub fn write(&self, key: &[u8]) -> Vec<u8> {
+        match self {
+            KeyFormat::Raw => key.to_vec(),
+            KeyFormat::Hex => {
+                let mut text: String = key.iter().map(|byte| format!("{:02x}", byte)).collect();
+                text.push('\n');
+                text.into_bytes()
+            }
+            KeyFormat::Base64 => {
+                let mut text = STANDARD.encode(key);
+                text.push('\n');
+                text.into_bytes()
+            }
+        }
+    }
+}
+
+fn hex_digit(digit: u8) -> Option<u8> {
+    (digit as char).to_digit(16).map(|value| value as u8)
+}
+
+impl Display for KeyFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyFormat::Raw => write!(f, "raw"),
+            KeyFormat::Hex => write!(f, "hex"),
+            KeyFormat::Base64 => write!(f, "base64"),
+        }
+    }
+}
+
+impl FromStr for KeyFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        KeyFormat::ALL
+            .into_iter()
+            .find(|format| format.to_string().eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("Unknown key format {name}, use raw, hex or base64"))
+    }
+}
+
+impl Display for KeyFormatError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyFormatError::Empty => write!(f, "Key is empty"),
+            KeyFormatError::Hex => write!(f, "Key is not valid hexadecimal"),
+            KeyFormatError::Base64(error) => write!(f, "Key is not valid base64: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for KeyFormatError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn written_keys_read_back() {
+        let key: Vec<u8> = (0..=255).chain([0, 10, 32, 255]).collect();
+        for format in KeyFormat::ALL {
+            assert_eq!(
+                format.read(&format.write(&key)),
+                Ok(key.clone()),
+                "{format}"
+            );
+        }
+        assert_eq!(
+            KeyFormat::Hex.read(b" 0a FF\n10 "),
+            Ok(vec![0x0a, 0xff, 0x10])
+        );
+        assert_eq!(KeyFormat::Base64.read(b"AAr/\n"), Ok(vec![0, 10, 255]));
+    }
+
+    #[test]
+    fn malformed_keys_are_rejected() {
+        assert_eq!(KeyFormat::Hex.read(b"abc"), Err(KeyFormatError::Hex));
+        assert_eq!(KeyFormat::Hex.read(b"0g"), Err(KeyFormatError::Hex));
+        assert_eq!(KeyFormat::Hex.read(b"+1"), Err(KeyFormatError::Hex));
+        assert!(matches!(
+            KeyFormat::Base64.read(b"AA*="),
+            Err(KeyFormatError::Base64(_))
+        ));
+        assert!(matches!(
+            KeyFormat::Base64.read(b"AAA"),
+            Err(KeyFormatError::Base64(_))
+        ));
+        for format in KeyFormat::ALL {
+            assert_eq!(format.read(b""), Err(KeyFormatError::Empty), "{format}");
+        }
+        assert_eq!(KeyFormat::Hex.read(b" \n"), Err(KeyFormatError::Empty));
+        assert_eq!(KeyFormat::Base64.read(b"\n"), Err(KeyFormatError::Empty));
+    }
+}
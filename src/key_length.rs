@@ -0,0 +1,257 @@
+use crate::parallel;
+use egui::ahash::{HashMap, HashMapExt};
+use std::num::NonZeroUsize;
+
+/// A length stands for its multiples if it scores at least this fraction of the best of them.
+const DIVISOR_TOLERANCE: f64 = 0.7;
+
+/// Fewest bytes per key position for a length to be considered, shorter columns are noise.
+const MIN_ROWS: usize = 16;
+
+/// Most bytes per key position sampled, more only slow the estimate down.
+/// It keeps estimating multi-megabyte files quick enough to run on opening them.
+const MAX_ROWS: usize = 2048;
+
+/// Largest spread of a signal over lengths, relative to its maximum, still taken as flat.
+const FLAT_SPREAD: f64 = 0.2;
+
+/// Weight of the index of coincidence against the Hamming distance and the Kasiski examination.
+/// It is the most reliable of them, the others are skewed by neighbouring plaintext bytes.
+const COINCIDENCE_WEIGHT: f64 = 4.0;
+
+/// Length of sequences searched for by the Kasiski examination.
+const KASISKI_SEQUENCE_LENGTH: usize = 3;
+
+/// A candidate key length with its score.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyLengthCandidate {
+    /// Key length.
+    pub length: NonZeroUsize,
+    /// Score in range from 0 to 1, higher is better.
+    pub score: f64,
+}
+
+/// An estimator of the key length used in a repeating key XOR.
+pub struct KeyLengthEstimator {
+    max_length: NonZeroUsize,
+}
+
+impl KeyLengthEstimator {
+    /// Create a new estimator checking lengths up to the given maximum.
+    pub fn new(max_length: NonZeroUsize) -> Self {
+        Self { max_length }
+    }
+
+    /// Rank key lengths for the given contents, the best candidate comes first.
+    /// Only lengths leaving at least `MIN_ROWS` bytes for every key position are ranked,
+    /// and only the start of long contents, `MAX_ROWS` bytes per position, is sampled.
+    /// Returns empty list if contents are too short to estimate anything.
+    pub fn estimate(&self, contents: &[u8]) -> Vec<KeyLengthCandidate> {
+        let sample_size = self.max_length.get().saturating_mul(MAX_ROWS);
+        let contents = &contents[..contents.len().min(sample_size)];
+        let max_length = self.max_length.get().min(contents.len() / MIN_ROWS);
+        if max_length == 0 {
+            return Vec::new();
+        }
+
+        // Lengths are independent, so every one is scored on its own.
+        let coincidence = normalize(parallel::map(1..max_length + 1, |length| {
+            index_of_coincidence(contents, length)
+        }));
+        let hamming = normalize(parallel::map(1..max_length + 1, |length| {
+            1.0 - hamming_distance(contents, length)
+        }));
+        let kasiski = normalize(kasiski_examination(contents, max_length));
+
+        let scores: Vec<f64> = (0..max_length)
+            .map(|index| {
+                (COINCIDENCE_WEIGHT * coincidence[index] + hamming[index] + kasiski[index])
+                    / (COINCIDENCE_WEIGHT + 2.0)
+            })
+            .collect();
+
+        // Multiples of the key length fit as well as the key length itself, so a length close
+        // to its best multiple takes its score, and the smallest such length comes first.
+        let mut candidates: Vec<_> = (1..=max_length)
+            .map(|length| {
+                let best_multiple = (length..=max_length)
+                    .step_by(length)
+                    .map(|multiple| scores[multiple - 1])
+                    .fold(f64::NEG_INFINITY, f64::max);
+                let score = scores[length - 1];
+                let score = if score >= DIVISOR_TOLERANCE * best_multiple {
+                    best_multiple
+                } else {
+                    score
+                };
+                (length, score)
+            })
+            .collect();
+        // A length standing for the same multiple as its divisor only repeats it.
+        for index in 0..candidates.len() {
+            let (length, score) = candidates[index];
+            let repeats_divisor = (1..length)
+                .filter(|divisor| length % divisor == 0)
+                .any(|divisor| candidates[divisor - 1].1 >= score);
+            if repeats_divisor {
+                candidates[index].1 *= DIVISOR_TOLERANCE;
+            }
+        }
+
+        let mut candidates: Vec<_> = candidates
+            .into_iter()
+            .filter_map(|(length, score)| {
+                NonZeroUsize::new(length).map(|length| KeyLengthCandidate { length, score })
+            })
+            .collect();
+        // Stable sort keeps shorter lengths first for equal scores.
+        candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+        candidates
+    }
+
+    /// Get the most probable key length for the given contents.
+    pub fn best(&self, contents: &[u8]) -> Option<NonZeroUsize> {
+        self.estimate(contents)
+            .first()
+            .map(|candidate| candidate.length)
+    }
+}
+
+impl Default for KeyLengthEstimator {
+    fn default() -> Self {
+        Self::new(NonZeroUsize::new(256).unwrap())
+    }
+}
+
+/// Scale values to range from 0 to 1.
+/// Values spreading less than `FLAT_SPREAD` of the largest one are only noise, every length
+/// fits equally well then, as under a single byte key, so they all become 1.
+fn normalize(values: Vec<f64>) -> Vec<f64> {
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+    if max - min <= FLAT_SPREAD * max.abs() + f64::EPSILON {
+        return vec![1.0; values.len()];
+    }
+
+    values
+        .into_iter()
+        .map(|value| (value - min) / (max - min))
+        .collect()
+}
+
+/// Average index of coincidence of columns. Bytes in one column share the key byte,
+/// so the column keeps the distribution of the plaintext language.
+fn index_of_coincidence(contents: &[u8], length: usize) -> f64 {
+    let mut total = 0.0;
+
+    for column in 0..length {
+        let mut counts = [0usize; 256];
+        let mut size = 0usize;
+        for byte in contents.iter().skip(column).step_by(length) {
+            counts[*byte as usize] += 1;
+            size += 1;
+        }
+
+        if size > 1 {
+            let pairs: usize = counts
+                .iter()
+                .map(|count| count * count.saturating_sub(1))
+                .sum();
+            total += pairs as f64 / (size * (size - 1)) as f64;
+        }
+    }
+
+    total / length as f64
+}
+
+/// Average Hamming distance between neighbouring blocks, normalized to the bit count.
+fn hamming_distance(contents: &[u8], length: usize) -> f64 {
+    let blocks: Vec<_> = contents.chunks_exact(length).collect();
+    let mut distance = 0;
+    let mut compared = 0;
+
+    for pair in blocks.windows(2) {
+        for (first, second) in pair[0].iter().zip(pair[1]) {
+            distance += (first ^ second).count_ones() as usize;
+        }
+        compared += length * 8;
+    }
+
+    if compared == 0 {
+        1.0
+    } else {
+        distance as f64 / compared as f64
+    }
+}
+
+/// Fraction of spacings between repeated sequences divisible by each key length,
+/// relative to the fraction expected by chance.
+fn kasiski_examination(contents: &[u8], max_length: usize) -> Vec<f64> {
+    let mut last_seen = HashMap::new();
+    let mut spacings = Vec::new();
+
+    for (index, sequence) in contents.windows(KASISKI_SEQUENCE_LENGTH).enumerate() {
+        if let Some(previous) = last_seen.insert(sequence, index) {
+            spacings.push(index - previous);
+        }
+    }
+
+    parallel::map(1..max_length + 1, |length| {
+        if spacings.is_empty() {
+            0.0
+        } else {
+            let divisible = spacings
+                .iter()
+                .filter(|spacing| *spacing % length == 0)
+                .count();
+            (divisible * length) as f64 / spacings.len() as f64
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_random::Random;
+    use crate::{Cipher, TextEncoding, WORDS};
+
+    /// Random Polish dictionary words of the given size in Windows-1250.
+    fn plaintext(random: &mut Random, size: usize) -> Vec<u8> {
+        let words: Vec<Vec<u8>> = WORDS
+            .lines()
+            .filter_map(|word| TextEncoding::WINDOWS1250.encode(word))
+            .collect();
+        let mut plaintext = Vec::with_capacity(size);
+        while plaintext.len() < size {
+            plaintext.extend(&words[random.below(words.len())]);
+            plaintext.push(b' ');
+        }
+        plaintext.truncate(size);
+        plaintext
+    }
+
+    /// The true key length ranks first, not one of its multiples, also for short texts.
+    #[test]
+    fn true_length_ranks_first() {
+        let mut random = Random(0x2545_F491_4F6C_DD1D);
+
+        for size in [2 * 1024, 8 * 1024] {
+            for key_length in [1, 2, 3, 5, 7, 12, 13, 32, 64, 100] {
+                for _ in 0..2 {
+                    let key = (0..key_length).map(|_| random.next() as u8).collect();
+                    let contents = Cipher::new(key)
+                        .expect("key is not empty")
+                        .encrypt(&plaintext(&mut random, size));
+
+                    let best = KeyLengthEstimator::default().best(&contents);
+                    assert_eq!(
+                        best.map(NonZeroUsize::get),
+                        Some(key_length),
+                        "{size} bytes"
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use crate::{Alphabet, ByteSet, TextEncoding};
+use egui::ahash::{HashMap, HashMapExt};
+use serde::{Deserialize, Serialize};
+
+/// Extension of language pack files.
+pub const LANGUAGE_EXTENSION: &str = "language";
+
+/// Polish letters, numbers, and some punctuation used in the challenge.
+const POLISH_ALPHABET: &str = "aąbcćdeęfghijklłmnńoópqrsśtuvwxyzźżAĄBCĆDEĘFGHIJKLŁMNŃOÓPQRSŚTUVWXYZŹŻ0123456789!\"#%&()*,-.:;?@[] \r\n\u{a0}—’";
+
+/// English letters, numbers, and common punctuation.
+const ENGLISH_ALPHABET: &str =
+    "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789!\"#%&'()*,-.:;?@[] \r\n";
+
+/// English words ordered from the most frequent.
+const ENGLISH_WORDS: &str = include_str!("english_words.txt");
+
+/// Alphabet, dictionary and character statistics of a plaintext language.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LanguagePack {
+    /// Name shown to the user.
+    pub name: String,
+    /// Every character that may appear in the plaintext.
+    pub alphabet: String,
+    /// Dictionary words with their relative frequencies.
+    pub words: Vec<(String, f64)>,
+    /// Character unigrams and bigrams with their relative frequencies.
+    /// A space stands for a word boundary. Counted from the words if empty.
+    #[serde(default)]
+    pub ngrams: Vec<(String, f64)>,
+}
+
+impl LanguagePack {
+    /// Built-in Polish language, word frequencies follow Zipf's law.
+    pub fn polish() -> Self {
+        Self::from_word_list("Polish", POLISH_ALPHABET, crate::WORDS, zipf)
+    }
+
+    /// Built-in English language, word frequencies follow Zipf's law.
+    pub fn english() -> Self {
+        Self::from_word_list("English", ENGLISH_ALPHABET, ENGLISH_WORDS, zipf)
+    }
+
+    /// All built-in languages.
+    pub fn built_in() -> Vec<Self> {
+        vec![Self::polish(), Self::english()]
+    }
+
+    /// Load a language pack saved in RON.
+    pub fn load(text: &str) -> Result<Self, ron::error::SpannedError> {
+        ron::from_str(text)
+    }
+
+    /// Save the language pack to RON.
+    pub fn save(&self) -> Result<String, ron::Error> {
+        ron::ser::to_string_pretty(self, Default::default())
+    }
+
+    fn from_word_list(
+        name: &str,
+        alphabet: &str,
+        words: &str,
+        frequency: impl Fn(usize) -> f64,
+    ) -> Self {
+        let words: Vec<(String, f64)> = words
+            .lines()
+            .filter(|word| !word.is_empty())
+            .enumerate()
+            .map(|(rank, word)| (word.to_string(), frequency(rank)))
+            .collect();
+        Self {
+            name: name.to_string(),
+            alphabet: alphabet.to_string(),
+            ngrams: count_ngrams(&words),
+            words,
+        }
+    }
+
+    /// Bytes of alphabet characters in the given encoding.
+    /// Characters the encoding cannot represent are skipped.
+    pub fn alphabet(&self, encoding: &TextEncoding) -> ByteSet {
+        Alphabet::literal(&self.alphabet).bytes(encoding)
+    }
+
+    /// Words in the given encoding, the most frequent first.
+    /// Words the encoding cannot represent are skipped.
+    pub fn words(&self, encoding: &TextEncoding) -> Vec<Vec<u8>> {
+        self.words_with_frequencies(encoding)
+            .into_iter()
+            .map(|(word, _)| word)
+            .collect()
+    }
+
+    /// Words in the given encoding with their frequencies, the most frequent first.
+    /// Words the encoding cannot represent are skipped.
+    pub fn words_with_frequencies(&self, encoding: &TextEncoding) -> Vec<(Vec<u8>, f64)> {
+        let mut words: Vec<&(String, f64)> = self.words.iter().collect();
+        words.sort_by(|a, b| b.1.total_cmp(&a.1));
+        words
+            .into_iter()
+            .filter_map(|(word, frequency)| Some((encoding.encode(word)?, *frequency)))
+            .collect()
+    }
+
+    /// Character unigrams and bigrams with their frequencies, counted from the words if missing.
+    pub fn ngrams(&self) -> Vec<(String, f64)> {
+        if self.ngrams.is_empty() {
+            count_ngrams(&self.words)
+        } else {
+            self.ngrams.clone()
+        }
+    }
+}
+
+impl Default for LanguagePack {
+    fn default() -> Self {
+        Self::polish()
+    }
+}
+
+impl std::fmt::Display for LanguagePack {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// Relative frequency of the word at the rank of a list ordered from the most frequent.
+fn zipf(rank: usize) -> f64 {
+    1.0 / (rank + 1) as f64
+}
+
+/// Count unigrams and bigrams of words weighted by their frequencies.
+/// Every word is counted as if it was surrounded by spaces.
+fn count_ngrams(words: &[(String, f64)]) -> Vec<(String, f64)> {
+    let mut counts: HashMap<String, f64> = HashMap::new();
+
+    for (word, frequency) in words.iter().filter(|(word, _)| !word.is_empty()) {
+        let characters: Vec<char> = format!(" {} ", word).chars().collect();
+        for character in &characters[1..] {
+            *counts.entry(character.to_string()).or_default() += frequency;
+        }
+        for pair in characters.windows(2) {
+            *counts.entry(pair.iter().collect()).or_default() += frequency;
+        }
+    }
+
+    let mut ngrams: Vec<_> = counts.into_iter().collect();
+    ngrams.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ngrams
+}
@@ -0,0 +1,80 @@
+use crate::{LanguagePack, TextEncoding};
+
+/// Weight added to every n-gram count, so unseen bytes are unlikely but possible.
+const SMOOTHING: f64 = 0.01;
+
+/// Character unigram and bigram statistics of a language.
+/// Probabilities are stored as natural logarithms.
+pub struct LanguageModel {
+    unigrams: Vec<f64>,
+    bigrams: Vec<f64>,
+}
+
+impl LanguageModel {
+    /// Create a model from n-grams of the language encoded in the target encoding.
+    /// A character spanning several bytes also counts the bigrams inside it.
+    /// Frequencies are scaled to average one, so smoothing does not depend on their units.
+    pub fn new(language: &LanguagePack, encoding: &TextEncoding) -> Self {
+        let mut unigrams = vec![SMOOTHING; 256];
+        let mut bigrams = vec![SMOOTHING; 256 * 256];
+
+        let ngrams = language.ngrams();
+        let total: f64 = ngrams.iter().map(|(_, frequency)| frequency).sum();
+        let scale = if total > 0.0 {
+            ngrams.len() as f64 / total
+        } else {
+            0.0
+        };
+
+        for (ngram, frequency) in ngrams {
+            let frequency = frequency * scale;
+            let characters: Option<Vec<Vec<u8>>> = ngram
+                .chars()
+                .map(|character| encoding.encode(character.encode_utf8(&mut [0; 4])))
+                .collect();
+            match characters.as_deref() {
+                Some([character]) => {
+                    for byte in character {
+                        unigrams[*byte as usize] += frequency;
+                    }
+                    for pair in character.windows(2) {
+                        bigrams[bigram_index(pair[0], pair[1])] += frequency;
+                    }
+                }
+                Some([first, second]) => {
+                    if let (Some(first), Some(second)) = (first.last(), second.first()) {
+                        bigrams[bigram_index(*first, *second)] += frequency;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Self {
+            unigrams: log_probabilities(unigrams),
+            bigrams: log_probabilities(bigrams),
+        }
+    }
+
+    /// Log probability of the byte.
+    pub fn unigram(&self, byte: u8) -> f64 {
+        self.unigrams[byte as usize]
+    }
+
+    /// Log probability of the pair of neighbouring bytes.
+    pub fn bigram(&self, first: u8, second: u8) -> f64 {
+        self.bigrams[bigram_index(first, second)]
+    }
+}
+
+fn bigram_index(first: u8, second: u8) -> usize {
+    ((first as usize) << 8) | second as usize
+}
+
+fn log_probabilities(counts: Vec<f64>) -> Vec<f64> {
+    let total: f64 = counts.iter().sum();
+    counts
+        .into_iter()
+        .map(|count| (count / total).ln())
+        .collect()
+}
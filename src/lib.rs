@@ -1,52 +1,60 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
-use egui::ahash::HashSet;
-use encoding::all::WINDOWS_1250;
-use encoding::{EncoderTrap, Encoding};
-
+mod alphabet;
+mod byte_set;
+mod byte_transitions;
 mod cipher;
 mod cracker;
+mod crib;
+mod encoding_detector;
+mod error;
+mod key_format;
+mod key_length;
+mod language;
+mod language_model;
+mod parallel;
 mod potential_key;
+mod session;
+#[cfg(test)]
+mod test_random;
 mod text_encoding;
 pub mod ui;
+mod word_matcher;
 
+pub use alphabet::*;
+pub use byte_set::*;
+pub use byte_transitions::*;
 pub use cipher::*;
 pub use cracker::*;
+pub use crib::*;
+pub use encoding_detector::*;
+pub use error::*;
+pub use key_format::*;
+pub use key_length::*;
+pub use language::*;
+pub use language_model::*;
 pub use potential_key::*;
+pub use session::*;
 pub use text_encoding::*;
+pub use word_matcher::*;
 
-/// The alphabet used in the challenge. It's Polish letters, numbers, and some punctuation.
-const ALPHABET: &str = "aąbcćdeęfghijklłmnńoópqrsśtuvwxyzźżAĄBCĆDEĘFGHIJKLŁMNŃOÓPQRSŚTUVWXYZŹŻ0123456789!\"#%&()*,-.:;?@[] \r\n\u{a0}—’";
-
+/// Polish top-1000 words, the dictionary of the built-in Polish language.
 pub const WORDS: &str = include_str!("words.txt");
 
 /// Returns a set of bytes that are valid in UTF-8.
-pub fn utf8_alphabet() -> HashSet<u8> {
-    let mut bytes: HashSet<_> = ALPHABET.as_bytes().iter().cloned().collect();
-    bytes.extend(&[169, 166, 171]);
-    bytes
+pub fn utf8_alphabet() -> ByteSet {
+    LanguagePack::polish().alphabet(&TextEncoding::UTF8)
 }
 
 pub fn utf8_words() -> Vec<Vec<u8>> {
-    WORDS
-        .split('\n')
-        .map(str::as_bytes)
-        .map(|x| x.to_vec())
-        .collect()
+    LanguagePack::polish().words(&TextEncoding::UTF8)
 }
 
 /// Returns a set of bytes that are valid in Windows-1250.
-pub fn windows1250_alphabet() -> HashSet<u8> {
-    WINDOWS_1250
-        .encode(ALPHABET, EncoderTrap::Strict)
-        .unwrap()
-        .into_iter()
-        .collect()
+pub fn windows1250_alphabet() -> ByteSet {
+    LanguagePack::polish().alphabet(&TextEncoding::WINDOWS1250)
 }
 
 pub fn windows1250_words() -> Vec<Vec<u8>> {
-    WORDS
-        .split('\n')
-        .map(|x| WINDOWS_1250.encode(x, EncoderTrap::Strict).unwrap())
-        .collect()
+    LanguagePack::polish().words(&TextEncoding::WINDOWS1250)
 }
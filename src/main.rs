@@ -2,36 +2,161 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 #[cfg(not(target_arch = "wasm32"))]
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 use many_time_pad::ui::Application;
 #[cfg(not(target_arch = "wasm32"))]
-use many_time_pad::{Cipher, Cracker, TextEncoding};
+use many_time_pad::{
+    hash_bytes, Alphabet, Cipher, Cracker, EncodingDetector, KeyFormat, KeyLengthEstimator,
+    LanguagePack, PotentialKey, Session, SessionCiphertext, TextEncoding,
+};
 #[cfg(not(target_arch = "wasm32"))]
 use std::env::args;
 #[cfg(not(target_arch = "wasm32"))]
 use std::error::Error;
 #[cfg(not(target_arch = "wasm32"))]
+use std::io::{Read, Write};
+#[cfg(not(target_arch = "wasm32"))]
 use std::num::NonZeroUsize;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
 
 #[cfg(not(target_arch = "wasm32"))]
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
-    /// Path to input file
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Crack inputs encrypted with the same key
+    Crack(CrackArgs),
+    /// Encrypt the input with the key
+    Encrypt(CipherArgs),
+    /// Decrypt the input with the key
+    Decrypt(CipherArgs),
+    /// Estimate the key length and rank encodings by how well the input cracks under them
+    Analyze(AnalyzeArgs),
+    /// Estimate the key length
+    Keylen(KeylenArgs),
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(clap::Args, Debug)]
+pub struct CrackArgs {
+    /// Path to input file or - for stdin, several inputs are cracked as messages encrypted
+    /// with the same key
+    #[arg(short, long, required = true)]
+    pub input: Vec<String>,
+    /// Path to output file or - for stdout, one per input
+    #[arg(short, long, required = true)]
+    pub output: Vec<String>,
+    /// Encoding: windows1250, utf8, iso-8859-2, cp852, latin1, ascii, utf16le, utf16be,
+    /// or auto to detect it by cracking under every encoding
+    #[arg(short, long, value_parser = parse_encoding)]
+    pub encoding: EncodingArg,
+    #[command(flatten)]
+    pub language: LanguageArgs,
+    /// Key length, estimated from the input if omitted
+    #[arg(short, long)]
+    pub length_key: Option<NonZeroUsize>,
+    /// Longest key length considered when estimating it
+    #[arg(long, default_value = "256")]
+    pub max_length: NonZeroUsize,
+    /// Path to key output file or - for stdout
+    #[arg(short, long)]
+    pub key: Option<String>,
+    /// Format of the key file: raw, hex or base64
+    #[arg(long, default_value_t = KeyFormat::Raw)]
+    pub key_format: KeyFormat,
+    /// Path to session file, progress is loaded from it if it exists and saved to it after cracking
+    #[arg(short, long)]
+    pub session: Option<String>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(clap::Args, Debug)]
+pub struct CipherArgs {
+    /// Path to input file or - for stdin, it is processed as a stream
     #[arg(short, long)]
     pub input: String,
-    /// Path to output file
+    /// Path to output file or - for stdout
     #[arg(short, long)]
     pub output: String,
-    /// Encoding
+    /// Path to key file
     #[arg(short, long)]
-    pub encoding: TextEncoding,
-    /// Key length
-    #[arg(short, long, default_value = "256")]
-    pub length_key: NonZeroUsize,
-    /// Path to key output file
+    pub key: String,
+    /// Format of the key file: raw, hex or base64
+    #[arg(long, default_value_t = KeyFormat::Raw)]
+    pub key_format: KeyFormat,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(clap::Args, Debug)]
+pub struct AnalyzeArgs {
+    /// Path to input file or - for stdin
     #[arg(short, long)]
-    pub key: Option<String>,
+    pub input: String,
+    /// Encoding as in crack, every encoding is ranked if auto
+    #[arg(short, long, value_parser = parse_encoding, default_value = "auto")]
+    pub encoding: EncodingArg,
+    #[command(flatten)]
+    pub language: LanguageArgs,
+    /// Key length, estimated from the input if omitted
+    #[arg(short, long)]
+    pub length_key: Option<NonZeroUsize>,
+    /// Longest key length considered when estimating it
+    #[arg(long, default_value = "256")]
+    pub max_length: NonZeroUsize,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(clap::Args, Debug)]
+pub struct KeylenArgs {
+    /// Path to input file or - for stdin
+    #[arg(short, long)]
+    pub input: String,
+    /// Number of key lengths shown
+    #[arg(short, long, default_value_t = 5)]
+    pub count: usize,
+    /// Longest key length considered when estimating it
+    #[arg(long, default_value = "256")]
+    pub max_length: NonZeroUsize,
+}
+
+/// Plaintext language and alphabet given on the command line.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(clap::Args, Debug)]
+pub struct LanguageArgs {
+    /// Plaintext language, a built-in name or a path to a language pack file
+    #[arg(long, default_value = "polish")]
+    pub language: String,
+    /// Plaintext alphabet replacing the language alphabet, explicit characters and classes
+    /// like [:letters:], [:digits:], [:punctuation:] or [:whitespace:]
+    #[arg(short, long, conflicts_with = "alphabet_file")]
+    pub alphabet: Option<Alphabet>,
+    /// Path to a file with the plaintext alphabet expression
+    #[arg(long)]
+    pub alphabet_file: Option<String>,
+}
+
+/// Encoding given on the command line.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone, Debug)]
+pub enum EncodingArg {
+    Auto,
+    Fixed(TextEncoding),
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn parse_encoding(value: &str) -> Result<EncodingArg, String> {
+    if value.eq_ignore_ascii_case("auto") {
+        Ok(EncodingArg::Auto)
+    } else {
+        TextEncoding::from_str(value, true).map(EncodingArg::Fixed)
+    }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -40,7 +165,10 @@ fn main() -> eframe::Result<()> {
 
     #[cfg(not(target_arch = "wasm32"))]
     if args().len() > 1 {
-        cli_main().expect("Failed to run CLI:");
+        if let Err(error) = cli_main() {
+            eprintln!("Error: {error}");
+            std::process::exit(1);
+        }
         return Ok(());
     }
 
@@ -51,32 +179,314 @@ fn main() -> eframe::Result<()> {
     eframe::run_native(
         "Many Time Pad",
         native_options,
-        Box::new(|_| Box::<Application>::default()),
+        Box::new(|context| Box::new(Application::new(context))),
     )
 }
 
 #[cfg(not(target_arch = "wasm32"))]
 fn cli_main() -> Result<(), Box<dyn Error>> {
-    let args = Args::parse();
+    match Args::parse().command {
+        Command::Crack(args) => crack(args),
+        Command::Encrypt(args) | Command::Decrypt(args) => apply_key(args),
+        Command::Analyze(args) => analyze(args),
+        Command::Keylen(args) => keylen(args),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn crack(args: CrackArgs) -> Result<(), Box<dyn Error>> {
+    if args.input.len() != args.output.len() {
+        return Err("Number of outputs must match number of inputs".into());
+    }
+    let stdin_inputs = args.input.iter().filter(|path| *path == STANDARD_STREAM);
+    if stdin_inputs.count() > 1 {
+        return Err("Standard input can be read only once".into());
+    }
 
-    let input = std::fs::read(args.input)?;
-    let cracker = Cracker::new(&args.encoding);
-    let key = cracker.crack(&input, args.length_key).get_current_key();
-    let message = Cipher::new(key.clone()).decrypt(&input);
-    let message = args
-        .encoding
-        .decode(&message)
-        .ok_or("Failed to decode message")?;
+    let inputs = args
+        .input
+        .iter()
+        .map(|path| read_input(path))
+        .collect::<Result<Vec<_>, _>>()?;
+    let (language, alphabet) = args.language.load()?;
+    let encoding = match &args.encoding {
+        EncodingArg::Auto => None,
+        EncodingArg::Fixed(encoding) => Some(encoding.clone()),
+    };
+
+    let (encoding, key) = if let [input] = inputs.as_slice() {
+        let (encoding, potential_key) = match args
+            .session
+            .as_deref()
+            .filter(|path| Path::new(path).exists())
+        {
+            Some(path) => load_session(path, input, encoding.as_ref())?,
+            None => {
+                let key_length = match args.length_key {
+                    Some(key_length) => key_length,
+                    None => estimate_key_length(input, args.max_length)?,
+                };
+                match encoding {
+                    Some(encoding) => {
+                        let key = cracker(&encoding, &language, alphabet.as_ref())
+                            .crack(input, key_length)?;
+                        (encoding, key)
+                    }
+                    None => {
+                        let candidate =
+                            EncodingDetector::new(language, alphabet).best(input, key_length)?;
+                        eprintln!("Detected encoding: {}", candidate.encoding);
+                        (candidate.encoding, candidate.key)
+                    }
+                }
+            }
+        };
 
-    std::fs::write(args.output, message)?;
+        if let Some(path) = &args.session {
+            // Standard input can not be read again, so it is kept in the session.
+            let ciphertext = if args.input[0] == STANDARD_STREAM {
+                SessionCiphertext::Embedded {
+                    name: "stdin".to_owned(),
+                    bytes: input.clone(),
+                }
+            } else {
+                // The session may be opened from another directory, so the path is absolute.
+                SessionCiphertext::File {
+                    path: std::fs::canonicalize(&args.input[0])?.display().to_string(),
+                    hash: hash_bytes(input),
+                }
+            };
+            let key_length = NonZeroUsize::new(potential_key.len()).ok_or("Key is empty")?;
+            let session = Session::new(
+                ciphertext,
+                encoding.clone(),
+                key_length,
+                potential_key.clone(),
+            );
+            std::fs::write(path, session.save()?)?;
+        }
+
+        (encoding, potential_key.get_current_key())
+    } else if args.session.is_some() {
+        return Err("Sessions support only one input".into());
+    } else {
+        let encoding = encoding.ok_or("Encoding detection supports only one input")?;
+        let inputs: Vec<&[u8]> = inputs.iter().map(Vec::as_slice).collect();
+        let key = cracker(&encoding, &language, alphabet.as_ref())
+            .crack_many(&inputs)?
+            .get_current_key();
+        (encoding, key)
+    };
+
+    let cipher = Cipher::new(key.clone())?;
+    for (input, output) in inputs.iter().zip(&args.output) {
+        let message = encoding
+            .decode(&cipher.decrypt(input))
+            .ok_or("Failed to decode message")?;
+        write_output(output, message.as_bytes())?;
+    }
 
     if let Some(key_path) = args.key {
-        std::fs::write(key_path, key)?;
+        write_output(&key_path, &args.key_format.write(&key))?;
+    }
+
+    Ok(())
+}
+
+/// Encrypt or decrypt the input, both are the same XOR with the key.
+#[cfg(not(target_arch = "wasm32"))]
+fn apply_key(args: CipherArgs) -> Result<(), Box<dyn Error>> {
+    let cipher = Cipher::new(args.key_format.read(&std::fs::read(&args.key)?)?)?;
+    let mut input = cipher.reader(open_input(&args.input)?);
+    let mut output = create_output(&args.output)?;
+    std::io::copy(&mut input, &mut output)?;
+    output.flush()?;
+    Ok(())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn analyze(args: AnalyzeArgs) -> Result<(), Box<dyn Error>> {
+    let input = read_input(&args.input)?;
+    let (language, alphabet) = args.language.load()?;
+
+    let key_length = match args.length_key {
+        Some(key_length) => {
+            println!("Key length: {}", key_length);
+            key_length
+        }
+        None => {
+            let candidate = KeyLengthEstimator::new(args.max_length)
+                .estimate(&input)
+                .into_iter()
+                .next()
+                .ok_or("Input is too short to estimate key length")?;
+            println!(
+                "Key length: {} (score {:.2})",
+                candidate.length, candidate.score
+            );
+            candidate.length
+        }
+    };
+
+    match args.encoding {
+        EncodingArg::Auto => {
+            println!("Encodings:");
+            for candidate in EncodingDetector::new(language, alphabet).detect(&input, key_length)? {
+                println!(
+                    "  {:<14}{:>6.2}  {}",
+                    candidate.encoding.to_string(),
+                    candidate.score,
+                    key_summary(&candidate.key)
+                );
+            }
+        }
+        EncodingArg::Fixed(encoding) => {
+            let key = cracker(&encoding, &language, alphabet.as_ref()).crack(&input, key_length)?;
+            println!("{}: {}", encoding, key_summary(&key));
+        }
     }
 
     Ok(())
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+fn keylen(args: KeylenArgs) -> Result<(), Box<dyn Error>> {
+    let input = read_input(&args.input)?;
+    if !print_key_lengths(&input, args.count, args.max_length) {
+        return Err("Input is too short to estimate key length".into());
+    }
+    Ok(())
+}
+
+/// Print the best key lengths with their scores, returns false if there are none.
+#[cfg(not(target_arch = "wasm32"))]
+fn print_key_lengths(input: &[u8], count: usize, max_length: NonZeroUsize) -> bool {
+    let candidates = KeyLengthEstimator::new(max_length).estimate(input);
+    for candidate in candidates.iter().take(count) {
+        println!("{:>6}  {:.2}", candidate.length, candidate.score);
+    }
+    !candidates.is_empty()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn estimate_key_length(
+    input: &[u8],
+    max_length: NonZeroUsize,
+) -> Result<NonZeroUsize, Box<dyn Error>> {
+    Ok(KeyLengthEstimator::new(max_length)
+        .best(input)
+        .ok_or("Input is too short to estimate key length")?)
+}
+
+/// Number of decoded and erroneous key positions.
+#[cfg(not(target_arch = "wasm32"))]
+fn key_summary(key: &PotentialKey) -> String {
+    let decoded = (0..key.len())
+        .filter(|index| key.is_decoded(*index))
+        .count();
+    let errors = (0..key.len()).filter(|index| key.is_error(*index)).count();
+    format!(
+        "{}/{} key bytes decoded, {} in error",
+        decoded,
+        key.len(),
+        errors
+    )
+}
+
+/// Path standing for the standard input or output.
+#[cfg(not(target_arch = "wasm32"))]
+const STANDARD_STREAM: &str = "-";
+
+#[cfg(not(target_arch = "wasm32"))]
+fn open_input(path: &str) -> std::io::Result<Box<dyn Read>> {
+    if path == STANDARD_STREAM {
+        Ok(Box::new(std::io::stdin().lock()))
+    } else {
+        Ok(Box::new(std::fs::File::open(path)?))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_input(path: &str) -> std::io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    open_input(path)?.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn create_output(path: &str) -> std::io::Result<Box<dyn Write>> {
+    if path == STANDARD_STREAM {
+        Ok(Box::new(std::io::stdout().lock()))
+    } else {
+        Ok(Box::new(std::io::BufWriter::new(std::fs::File::create(
+            path,
+        )?)))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_output(path: &str, bytes: &[u8]) -> std::io::Result<()> {
+    let mut output = create_output(path)?;
+    output.write_all(bytes)?;
+    output.flush()
+}
+
+/// Create a cracker, the alphabet replaces the language alphabet if given.
+#[cfg(not(target_arch = "wasm32"))]
+fn cracker(
+    encoding: &TextEncoding,
+    language: &LanguagePack,
+    alphabet: Option<&Alphabet>,
+) -> Cracker {
+    match alphabet {
+        Some(alphabet) => Cracker::with_alphabet(encoding, language, alphabet),
+        None => Cracker::with_language(encoding, language),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl LanguageArgs {
+    /// Load the language and the alphabet replacing its alphabet, if given.
+    fn load(&self) -> Result<(LanguagePack, Option<Alphabet>), Box<dyn Error>> {
+        let language = load_language(&self.language)?;
+        let alphabet = match &self.alphabet_file {
+            Some(path) => Some(Alphabet::load(&std::fs::read_to_string(path)?)?),
+            None => self.alphabet.clone(),
+        };
+        Ok((language, alphabet))
+    }
+}
+
+/// Find a built-in language by name or load a language pack from the path.
+#[cfg(not(target_arch = "wasm32"))]
+fn load_language(name: &str) -> Result<LanguagePack, Box<dyn Error>> {
+    if let Some(language) = LanguagePack::built_in()
+        .into_iter()
+        .find(|language| language.name.eq_ignore_ascii_case(name))
+    {
+        return Ok(language);
+    }
+    Ok(LanguagePack::load(&std::fs::read_to_string(name)?)?)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn load_session(
+    path: &str,
+    input: &[u8],
+    encoding: Option<&TextEncoding>,
+) -> Result<(TextEncoding, PotentialKey), Box<dyn Error>> {
+    let session = Session::load(&std::fs::read_to_string(path)?)?;
+
+    if session.read_ciphertext()? != input {
+        return Err("Session was saved for a different input".into());
+    }
+    if encoding.is_some_and(|encoding| session.encoding != *encoding) {
+        return Err("Session was saved with a different encoding".into());
+    }
+
+    Ok((session.encoding, session.key))
+}
+
 #[cfg(target_arch = "wasm32")]
 fn main() {
     eframe::WebLogger::init(log::LevelFilter::Debug).ok();
@@ -86,7 +496,7 @@ fn main() {
             .start(
                 "the_canvas_id",
                 eframe::WebOptions::default(),
-                Box::new(|_| Box::<Application>::default()),
+                Box::new(|context| Box::new(Application::new(context))),
             )
             .await
             .expect("failed to start application");
@@ -0,0 +1,36 @@
+use std::ops::Range;
+
+/// Number of independent work items processed between progress reports.
+pub(crate) const BATCH_SIZE: usize = 64;
+
+/// Map every index of the range, spread across CPU cores with the `parallel` feature on native
+/// targets and sequentially otherwise. Results keep the order of the range.
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+pub(crate) fn map<R, F>(range: Range<usize>, f: F) -> Vec<R>
+where
+    R: Send,
+    F: Fn(usize) -> R + Sync + Send,
+{
+    use rayon::prelude::*;
+
+    range.into_par_iter().map(f).collect()
+}
+
+/// Map every index of the range, spread across CPU cores with the `parallel` feature on native
+/// targets and sequentially otherwise. Results keep the order of the range.
+#[cfg(not(all(feature = "parallel", not(target_arch = "wasm32"))))]
+pub(crate) fn map<R, F>(range: Range<usize>, f: F) -> Vec<R>
+where
+    R: Send,
+    F: Fn(usize) -> R + Sync + Send,
+{
+    range.map(f).collect()
+}
+
+/// Batches of the range processed between progress reports.
+pub(crate) fn batches(range: Range<usize>) -> impl Iterator<Item = Range<usize>> {
+    let end = range.end;
+    range
+        .step_by(BATCH_SIZE)
+        .map(move |start| start..(start + BATCH_SIZE).min(end))
+}
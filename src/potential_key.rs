@@ -1,8 +1,97 @@
+use crate::{ByteSet, CribPlacement, Error};
+use serde::{Deserialize, Serialize};
+
 /// Potential key for the cipher.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "KeyFields")]
 pub struct PotentialKey {
     key: Vec<Vec<u8>>,
+    /// Possibilities of every position as a set, rebuilt from `key` when loaded.
+    #[serde(skip)]
+    possible: Vec<ByteSet>,
+    scores: Vec<Vec<f64>>,
+    /// Weighted votes of dictionary words for every possibility.
+    votes: Vec<Vec<f64>>,
+    positions: Vec<usize>,
+    uncertain: Vec<bool>,
+    #[serde(default)]
+    history: Vec<KeyEdit>,
+    #[serde(default)]
+    undone: Vec<KeyEdit>,
+}
+
+/// Saved fields of a potential key.
+#[derive(Deserialize)]
+struct KeyFields {
+    key: Vec<Vec<u8>>,
+    scores: Vec<Vec<f64>>,
+    #[serde(default)]
+    votes: Vec<Vec<f64>>,
     positions: Vec<usize>,
     uncertain: Vec<bool>,
+    #[serde(default)]
+    history: Vec<KeyEdit>,
+    #[serde(default)]
+    undone: Vec<KeyEdit>,
+}
+
+/// Kind of a recorded key edit.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum KeyEditKind {
+    /// Value was chosen with [`PotentialKey::set_value`].
+    SetValue(u8),
+    /// Value was confirmed with [`PotentialKey::accept_value`].
+    AcceptValue(u8),
+}
+
+/// State of one key position.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct PositionState {
+    key: Vec<u8>,
+    scores: Vec<f64>,
+    #[serde(default)]
+    votes: Vec<f64>,
+    position: usize,
+    uncertain: bool,
+}
+
+/// A further key position changed by the same edit.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct PositionEdit {
+    index: usize,
+    before: PositionState,
+    after: PositionState,
+}
+
+/// A recorded key edit that can be undone and redone.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KeyEdit {
+    /// First edited key position.
+    pub index: usize,
+    /// What was done to the first edited position.
+    pub kind: KeyEditKind,
+    before: PositionState,
+    after: PositionState,
+    /// Other positions set together with the first one, like the rest of a character.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    further: Vec<PositionEdit>,
+}
+
+impl std::fmt::Display for KeyEdit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            KeyEditKind::SetValue(value) => write!(f, "Set {} to {:02X?}", self.index, value)?,
+            KeyEditKind::AcceptValue(value) => {
+                write!(f, "Confirm {} as {:02X?}", self.index, value)?
+            }
+        }
+        for edit in &self.further {
+            if let Some(value) = edit.after.key.get(edit.after.position) {
+                write!(f, ", {} to {:02X?}", edit.index, value)?;
+            }
+        }
+        Ok(())
+    }
 }
 
 impl PotentialKey {
@@ -11,17 +100,47 @@ impl PotentialKey {
         Self {
             positions: vec![0; key.len()],
             uncertain: vec![true; key.len()],
+            scores: key.iter().map(|values| vec![0.0; values.len()]).collect(),
+            votes: key.iter().map(|values| vec![0.0; values.len()]).collect(),
+            history: Vec::new(),
+            undone: Vec::new(),
+            possible: key.iter().map(|values| values.iter().collect()).collect(),
             key,
         }
     }
 
-    /// Get the key.
-    pub fn get_current_key(&self) -> Vec<u8> {
-        let mut key = Vec::with_capacity(self.key.len());
-        for (index, value) in self.key.iter().enumerate() {
-            key.push(*value.get(self.positions[index]).unwrap_or(&0));
+    /// Create a new key from candidates with scores, best candidates first.
+    pub fn with_scores(key: Vec<Vec<(u8, f64)>>) -> Self {
+        let (key, scores): (Vec<Vec<u8>>, Vec<Vec<f64>>) = key
+            .into_iter()
+            .map(|values| values.into_iter().unzip())
+            .unzip();
+        Self {
+            scores,
+            ..Self::new(key)
         }
-        key
+    }
+
+    /// Get the key, positions without possible values are zero.
+    pub fn get_current_key(&self) -> Vec<u8> {
+        (0..self.key.len())
+            .map(|index| self.get_current_value(index).unwrap_or(0))
+            .collect()
+    }
+
+    /// Get the current value of this key position, none if it has no possible values.
+    pub fn get_current_value(&self, index: usize) -> Option<u8> {
+        self.key[index].get(self.positions[index]).copied()
+    }
+
+    /// Get the key length.
+    pub fn len(&self) -> usize {
+        self.key.len()
+    }
+
+    /// Is the key empty.
+    pub fn is_empty(&self) -> bool {
+        self.key.is_empty()
     }
 
     /// Is this key position only one possible option.
@@ -34,15 +153,64 @@ impl PotentialKey {
         self.uncertain[index]
     }
 
-    /// Set value for this key position.
-    pub fn set_value(&mut self, index: usize, value: u8) {
-        self.uncertain[index] = false;
-        self.positions[index] = self.key[index].iter().position(|&r| r == value).unwrap();
+    /// Set value for this key position and record it in the history.
+    /// The value must be one of the possibilities of the position.
+    pub fn set_value(&mut self, index: usize, value: u8) -> Result<(), Error> {
+        self.set_values(&[(index, value)])
+    }
+
+    /// Set values for several key positions and record them as one edit, undone together.
+    /// Every value must be one of the possibilities of its position and every position must
+    /// appear once, otherwise the key is left unchanged.
+    pub fn set_values(&mut self, values: &[(usize, u8)]) -> Result<(), Error> {
+        let Some(&(first, value)) = values.first() else {
+            return Ok(());
+        };
+        for (position, &(index, value)) in values.iter().enumerate() {
+            if index >= self.len() {
+                return Err(Error::KeyPosition {
+                    index,
+                    length: self.len(),
+                });
+            }
+            let repeated = values[..position].iter().any(|(other, _)| *other == index);
+            if repeated || !self.is_possible(index, value) {
+                return Err(Error::ImpossibleValue { index, value });
+            }
+        }
+
+        let before: Vec<PositionState> = values
+            .iter()
+            .map(|(index, _)| self.position_state(*index))
+            .collect();
+        for &(index, value) in values {
+            self.uncertain[index] = false;
+            self.positions[index] = self.key[index].iter().position(|&r| r == value).unwrap();
+        }
+        let mut before = before.into_iter();
+        let first_before = before.next().unwrap();
+        let further = values[1..]
+            .iter()
+            .zip(before)
+            .map(|((index, _), before)| PositionEdit {
+                index: *index,
+                before,
+                after: self.position_state(*index),
+            })
+            .collect();
+        self.record(first, KeyEditKind::SetValue(value), first_before, further);
+        Ok(())
     }
 
     /// Is value possible for this key position.
+    #[inline]
     pub fn is_possible(&self, index: usize, value: u8) -> bool {
-        self.key[index].contains(&value)
+        self.possible[index].contains(value)
+    }
+
+    /// Get all possible values for this key position as a set.
+    pub fn get_possible_set(&self, index: usize) -> ByteSet {
+        self.possible[index]
     }
 
     /// Is value not possible to decode.
@@ -55,11 +223,449 @@ impl PotentialKey {
         &self.key[index]
     }
 
-    /// Sets value for key index.
-    pub fn accept_value(&mut self, index: usize) {
+    /// Get scores of all possible values for this key position.
+    pub fn get_scores(&self, index: usize) -> &Vec<f64> {
+        &self.scores[index]
+    }
+
+    /// Get score of the value for this key position if it is possible.
+    pub fn get_score(&self, index: usize, value: u8) -> Option<f64> {
+        if !self.is_possible(index, value) {
+            return None;
+        }
+        self.key[index]
+            .iter()
+            .position(|&r| r == value)
+            .map(|position| self.scores[index][position])
+    }
+
+    /// Get word votes of all possible values for this key position.
+    pub fn get_votes(&self, index: usize) -> &Vec<f64> {
+        &self.votes[index]
+    }
+
+    /// Get word votes of the value for this key position if it is possible.
+    pub fn get_vote(&self, index: usize, value: u8) -> Option<f64> {
+        if !self.is_possible(index, value) {
+            return None;
+        }
+        self.key[index]
+            .iter()
+            .position(|&r| r == value)
+            .map(|position| self.votes[index][position])
+    }
+
+    /// Add a vote of a word placed over this key position, used while cracking.
+    /// The value must be one of the possibilities of the position.
+    pub(crate) fn add_vote(&mut self, index: usize, value: u8, weight: f64) {
+        let position = self.key[index].iter().position(|&r| r == value).unwrap();
+        self.votes[index][position] += weight;
+    }
+
+    /// Set every key position with votes to the value with the most votes, used while cracking.
+    /// Ties go to the value with the better score.
+    pub(crate) fn choose_by_votes(&mut self) {
+        for (index, votes) in self.votes.iter().enumerate() {
+            // Possibilities are sorted by score, so the first maximum has the best score.
+            let best = votes
+                .iter()
+                .enumerate()
+                .filter(|(_, votes)| **votes > 0.0)
+                .fold(
+                    None,
+                    |best: Option<(usize, f64)>, (position, votes)| match best {
+                        Some((_, best_votes)) if best_votes >= *votes => best,
+                        _ => Some((position, *votes)),
+                    },
+                );
+            if let Some((position, _)) = best {
+                self.positions[index] = position;
+                self.uncertain[index] = false;
+            }
+        }
+    }
+
+    /// Slide the crib over the contents and find placements where every implied key
+    /// byte is possible. Placements are sorted by score, best first.
+    pub fn drag_crib(&self, contents: &[u8], crib: &[u8]) -> Vec<CribPlacement> {
+        let key_length = self.key.len();
+        if crib.is_empty() || key_length == 0 || contents.len() < crib.len() {
+            return Vec::new();
+        }
+
+        let mut placements = Vec::new();
+
+        'offsets: for offset in 0..=(contents.len() - crib.len()) {
+            let key: Vec<u8> = crib
+                .iter()
+                .zip(&contents[offset..])
+                .map(|(plain, byte)| plain ^ byte)
+                .collect();
+
+            let mut score = 0.0;
+            for (index, value) in key.iter().enumerate() {
+                let position = (offset + index) % key_length;
+                if index >= key_length && key[index - key_length] != *value {
+                    continue 'offsets;
+                }
+                match self.get_score(position, *value) {
+                    Some(value_score) => score += value_score,
+                    None => continue 'offsets,
+                }
+            }
+
+            placements.push(CribPlacement {
+                offset,
+                score: score / key.len() as f64,
+                key,
+            });
+        }
+
+        placements.sort_by(|a, b| b.score.total_cmp(&a.score));
+        placements
+    }
+
+    /// Confirm the current value of this key position as its only possibility.
+    /// Fails if the position is out of range or has no possible values.
+    pub fn accept_value(&mut self, index: usize) -> Result<(), Error> {
+        if index >= self.len() {
+            return Err(Error::KeyPosition {
+                index,
+                length: self.len(),
+            });
+        }
+        if self.is_error(index) {
+            return Err(Error::NoPossibleValue { index });
+        }
+        let before = self.position_state(index);
         let value = self.key[index][self.positions[index]];
+        let score = self.scores[index][self.positions[index]];
+        let votes = self.votes[index][self.positions[index]];
         self.key[index].clear();
         self.key[index].push(value);
+        self.possible[index] = [value].iter().collect();
+        self.scores[index].clear();
+        self.scores[index].push(score);
+        self.votes[index].clear();
+        self.votes[index].push(votes);
         self.positions[index] = 0;
+        self.record(index, KeyEditKind::AcceptValue(value), before, Vec::new());
+        Ok(())
+    }
+
+    /// Get edits that can be undone, oldest first.
+    pub fn history(&self) -> &[KeyEdit] {
+        &self.history
+    }
+
+    /// Get edits that can be redone, the next one to redo last.
+    pub fn undone(&self) -> &[KeyEdit] {
+        &self.undone
+    }
+
+    /// Undo the last edit. Returns false if there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let Some(edit) = self.history.pop() else {
+            return false;
+        };
+        self.restore(edit.index, edit.before.clone());
+        for further in &edit.further {
+            self.restore(further.index, further.before.clone());
+        }
+        self.undone.push(edit);
+        true
+    }
+
+    /// Redo the last undone edit. Returns false if there is nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        let Some(edit) = self.undone.pop() else {
+            return false;
+        };
+        self.restore(edit.index, edit.after.clone());
+        for further in &edit.further {
+            self.restore(further.index, further.after.clone());
+        }
+        self.history.push(edit);
+        true
+    }
+
+    /// Undo or redo edits until the given number of edits is applied.
+    pub fn jump_to(&mut self, applied: usize) {
+        while self.history.len() > applied && self.undo() {}
+        while self.history.len() < applied && self.redo() {}
+    }
+
+    fn position_state(&self, index: usize) -> PositionState {
+        PositionState {
+            key: self.key[index].clone(),
+            scores: self.scores[index].clone(),
+            votes: self.votes[index].clone(),
+            position: self.positions[index],
+            uncertain: self.uncertain[index],
+        }
+    }
+
+    fn restore(&mut self, index: usize, mut state: PositionState) {
+        // Edits saved before votes were counted have none.
+        state.votes.resize(state.key.len(), 0.0);
+        self.possible[index] = state.key.iter().collect();
+        self.key[index] = state.key;
+        self.scores[index] = state.scores;
+        self.votes[index] = state.votes;
+        self.positions[index] = state.position;
+        self.uncertain[index] = state.uncertain;
+    }
+
+    fn record(
+        &mut self,
+        index: usize,
+        kind: KeyEditKind,
+        before: PositionState,
+        further: Vec<PositionEdit>,
+    ) {
+        let after = self.position_state(index);
+        self.undone.clear();
+        self.history.push(KeyEdit {
+            index,
+            kind,
+            before,
+            after,
+            further,
+        });
+    }
+}
+
+impl TryFrom<KeyFields> for PotentialKey {
+    type Error = String;
+
+    fn try_from(mut fields: KeyFields) -> Result<Self, String> {
+        let length = fields.key.len();
+        if fields.scores.len() != length
+            || fields.positions.len() != length
+            || fields.uncertain.len() != length
+            || (!fields.votes.is_empty() && fields.votes.len() != length)
+        {
+            return Err(format!("key fields do not all have {length} positions"));
+        }
+        // Keys saved before votes were counted have none.
+        fields.votes.resize(length, Vec::new());
+        for (votes, values) in fields.votes.iter_mut().zip(&fields.key) {
+            if votes.is_empty() {
+                votes.resize(values.len(), 0.0);
+            }
+        }
+
+        for index in 0..length {
+            check_position(
+                index,
+                &fields.key[index],
+                &fields.scores[index],
+                &fields.votes[index],
+                fields.positions[index],
+            )?;
+        }
+        for edit in fields.history.iter().chain(&fields.undone) {
+            let positions = [(edit.index, &edit.before, &edit.after)].into_iter().chain(
+                edit.further
+                    .iter()
+                    .map(|further| (further.index, &further.before, &further.after)),
+            );
+            for (index, before, after) in positions {
+                if index >= length {
+                    return Err(format!("edited key position {index} is out of range"));
+                }
+                for state in [before, after] {
+                    let votes = if state.votes.is_empty() {
+                        vec![0.0; state.key.len()]
+                    } else {
+                        state.votes.clone()
+                    };
+                    check_position(index, &state.key, &state.scores, &votes, state.position)?;
+                }
+            }
+        }
+
+        Ok(Self {
+            possible: fields
+                .key
+                .iter()
+                .map(|values| values.iter().collect())
+                .collect(),
+            key: fields.key,
+            scores: fields.scores,
+            votes: fields.votes,
+            positions: fields.positions,
+            uncertain: fields.uncertain,
+            history: fields.history,
+            undone: fields.undone,
+        })
+    }
+}
+
+/// Check candidates of a key position agree with their scores and votes, and the chosen
+/// candidate exists. A position without candidates keeps the first one chosen.
+fn check_position(
+    index: usize,
+    key: &[u8],
+    scores: &[f64],
+    votes: &[f64],
+    position: usize,
+) -> Result<(), String> {
+    if scores.len() != key.len() || votes.len() != key.len() {
+        return Err(format!(
+            "key position {index} has a different number of candidates, scores and votes"
+        ));
+    }
+    if position >= key.len().max(1) {
+        return Err(format!(
+            "chosen candidate of key position {index} is out of range"
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Snapshot = Vec<(Vec<u8>, Vec<f64>, Vec<f64>, ByteSet, usize, bool)>;
+
+    fn snapshot(key: &PotentialKey) -> Snapshot {
+        (0..key.len())
+            .map(|index| {
+                (
+                    key.key[index].clone(),
+                    key.scores[index].clone(),
+                    key.votes[index].clone(),
+                    key.possible[index],
+                    key.positions[index],
+                    key.uncertain[index],
+                )
+            })
+            .collect()
+    }
+
+    fn key() -> PotentialKey {
+        let mut key = PotentialKey::with_scores(vec![
+            vec![(1, -1.0), (2, -2.0), (3, -3.0)],
+            vec![(4, -0.5), (5, -1.5)],
+        ]);
+        key.add_vote(0, 3, 8.0);
+        key.add_vote(1, 5, 1.0);
+        key
+    }
+
+    #[test]
+    fn undo_and_redo_restore_positions() {
+        let mut key = key();
+        let initial = snapshot(&key);
+
+        key.set_value(0, 2).unwrap();
+        let set = snapshot(&key);
+        key.accept_value(0).unwrap();
+        let accepted = snapshot(&key);
+        assert_eq!(key.get_possibilities(0), &vec![2]);
+        assert_eq!(key.get_votes(0), &vec![0.0]);
+        assert!(!key.is_possible(0, 1));
+
+        assert!(key.undo());
+        assert_eq!(snapshot(&key), set);
+        assert!(key.undo());
+        assert_eq!(snapshot(&key), initial);
+        assert!(!key.undo());
+
+        assert!(key.redo());
+        assert_eq!(snapshot(&key), set);
+        assert!(key.redo());
+        assert_eq!(snapshot(&key), accepted);
+        assert!(!key.redo());
+
+        key.jump_to(0);
+        assert_eq!(snapshot(&key), initial);
+        assert_eq!(key.undone().len(), 2);
+        key.jump_to(2);
+        assert_eq!(snapshot(&key), accepted);
+        assert!(key.undone().is_empty());
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let mut key = key();
+        key.set_value(0, 3).unwrap();
+        key.set_value(1, 5).unwrap();
+        assert!(key.undo());
+        assert_eq!(key.undone().len(), 1);
+
+        key.accept_value(0).unwrap();
+        assert!(key.undone().is_empty());
+        assert!(!key.redo());
+        assert_eq!(key.history().len(), 2);
+        assert_eq!(key.get_current_key(), vec![3, 4]);
+        assert_eq!(key.get_votes(0), &vec![8.0]);
+    }
+
+    #[test]
+    fn invalid_edits_are_rejected_and_not_recorded() {
+        let mut key = key();
+        let initial = snapshot(&key);
+
+        assert_eq!(
+            key.set_value(2, 4),
+            Err(Error::KeyPosition {
+                index: 2,
+                length: 2
+            })
+        );
+        assert_eq!(
+            key.set_value(1, 3),
+            Err(Error::ImpossibleValue { index: 1, value: 3 })
+        );
+        assert_eq!(
+            key.accept_value(2),
+            Err(Error::KeyPosition {
+                index: 2,
+                length: 2
+            })
+        );
+        assert_eq!(snapshot(&key), initial);
+        assert!(key.history().is_empty());
+
+        let mut key = PotentialKey::new(vec![vec![7], Vec::new()]);
+        assert_eq!(
+            key.accept_value(1),
+            Err(Error::NoPossibleValue { index: 1 })
+        );
+        assert!(key.history().is_empty());
+    }
+
+    #[test]
+    fn set_values_is_undone_and_saved_as_one_edit() {
+        let mut key = key();
+        let initial = snapshot(&key);
+
+        assert_eq!(
+            key.set_values(&[(0, 3), (0, 2)]),
+            Err(Error::ImpossibleValue { index: 0, value: 2 })
+        );
+        assert_eq!(
+            key.set_values(&[(1, 5), (0, 7)]),
+            Err(Error::ImpossibleValue { index: 0, value: 7 })
+        );
+        assert_eq!(snapshot(&key), initial);
+
+        key.set_values(&[(1, 5), (0, 3)]).unwrap();
+        let set = snapshot(&key);
+        assert_eq!(key.get_current_key(), vec![3, 5]);
+        assert_eq!(key.history().len(), 1);
+        assert_eq!(key.history()[0].to_string(), "Set 1 to 05, 0 to 03");
+
+        let loaded: PotentialKey = ron::from_str(&ron::to_string(&key).unwrap()).unwrap();
+        assert_eq!(snapshot(&loaded), set);
+        assert_eq!(loaded.history(), key.history());
+
+        assert!(key.undo());
+        assert_eq!(snapshot(&key), initial);
+        assert!(key.redo());
+        assert_eq!(snapshot(&key), set);
     }
 }
@@ -0,0 +1,169 @@
+use crate::{PotentialKey, TextEncoding};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::num::NonZeroUsize;
+
+/// Version of the session format written by this build.
+pub const SESSION_VERSION: u32 = 1;
+
+/// Extension of session files.
+pub const SESSION_EXTENSION: &str = "session";
+
+/// Ciphertext the session was created for.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SessionCiphertext {
+    /// Ciphertext stored in the session.
+    Embedded { name: String, bytes: Vec<u8> },
+    /// Ciphertext stored in a file, identified by its hash.
+    File { path: String, hash: u64 },
+}
+
+/// Saved progress of cracking a ciphertext.
+#[derive(Serialize, Deserialize)]
+pub struct Session {
+    pub version: u32,
+    pub ciphertext: SessionCiphertext,
+    pub encoding: TextEncoding,
+    pub key_length: NonZeroUsize,
+    pub key: PotentialKey,
+}
+
+/// Error while reading or writing a session.
+#[derive(Debug)]
+pub enum SessionError {
+    /// Session was written by an unsupported version.
+    Version(u32),
+    /// Session file is malformed.
+    Format(String),
+    /// Ciphertext file changed since the session was saved.
+    Mismatch,
+    /// Ciphertext file can not be read.
+    Io(std::io::Error),
+}
+
+impl Session {
+    /// Create a new session in the current version.
+    pub fn new(
+        ciphertext: SessionCiphertext,
+        encoding: TextEncoding,
+        key_length: NonZeroUsize,
+        key: PotentialKey,
+    ) -> Self {
+        Self {
+            version: SESSION_VERSION,
+            ciphertext,
+            encoding,
+            key_length,
+            key,
+        }
+    }
+
+    /// Parse a session, rejecting keys that do not agree with themselves or the key length.
+    pub fn load(text: &str) -> Result<Self, SessionError> {
+        #[derive(Deserialize)]
+        struct Header {
+            version: u32,
+        }
+
+        let header: Header =
+            ron::from_str(text).map_err(|error| SessionError::Format(error.to_string()))?;
+        if header.version != SESSION_VERSION {
+            return Err(SessionError::Version(header.version));
+        }
+
+        let session: Session =
+            ron::from_str(text).map_err(|error| SessionError::Format(error.to_string()))?;
+        if session.key.len() != session.key_length.get() {
+            return Err(SessionError::Format(format!(
+                "key length {} does not match the key of {} bytes",
+                session.key_length,
+                session.key.len()
+            )));
+        }
+        Ok(session)
+    }
+
+    /// Serialize the session.
+    pub fn save(&self) -> Result<String, SessionError> {
+        ron::to_string(self).map_err(|error| SessionError::Format(error.to_string()))
+    }
+
+    /// Get the ciphertext, reading and verifying the file if it is not embedded.
+    pub fn read_ciphertext(&self) -> Result<Vec<u8>, SessionError> {
+        match &self.ciphertext {
+            SessionCiphertext::Embedded { bytes, .. } => Ok(bytes.clone()),
+            SessionCiphertext::File { path, hash } => {
+                let bytes = std::fs::read(path).map_err(SessionError::Io)?;
+                if hash_bytes(&bytes) == *hash {
+                    Ok(bytes)
+                } else {
+                    Err(SessionError::Mismatch)
+                }
+            }
+        }
+    }
+}
+
+impl Display for SessionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SessionError::Version(version) => write!(f, "Unsupported session version {version}"),
+            SessionError::Format(error) => write!(f, "Malformed session: {error}"),
+            SessionError::Mismatch => write!(f, "Ciphertext changed since the session was saved"),
+            SessionError::Io(error) => write!(f, "Failed to read ciphertext: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for SessionError {}
+
+/// Stable FNV-1a hash used to recognize ciphertext files.
+pub fn hash_bytes(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Session of a two byte key saved with the given key length.
+    fn session(key_length: usize) -> Session {
+        Session::new(
+            SessionCiphertext::Embedded {
+                name: "message".to_string(),
+                bytes: vec![1, 2, 3],
+            },
+            TextEncoding::WINDOWS1250,
+            NonZeroUsize::new(key_length).expect("key length is positive"),
+            PotentialKey::new(vec![vec![0x10, 0x20], vec![0x30]]),
+        )
+    }
+
+    /// A session whose key disagrees with itself or the key length is malformed, so it can not
+    /// be restored into a state that panics later.
+    #[test]
+    fn load_rejects_inconsistent_keys() {
+        let text = session(2).save().expect("session is serializable");
+        assert!(Session::load(&text).is_ok());
+
+        let text = session(5).save().expect("session is serializable");
+        assert!(matches!(Session::load(&text), Err(SessionError::Format(_))));
+
+        let text = session(2).save().expect("session is serializable");
+        for (field, broken) in [
+            ("positions:[0,0]", "positions:[]"),
+            ("positions:[0,0]", "positions:[2,0]"),
+            ("uncertain:[true,true]", "uncertain:[true]"),
+            ("scores:[[0.0,0.0],[0.0]]", "scores:[[0.0],[0.0]]"),
+        ] {
+            assert!(text.contains(field), "{text}");
+            let text = text.replace(field, broken);
+            assert!(
+                matches!(Session::load(&text), Err(SessionError::Format(_))),
+                "{broken}"
+            );
+        }
+    }
+}
@@ -1,63 +1,378 @@
-use egui::ahash::HashSet;
-use encoding::all::WINDOWS_1250;
-use encoding::{DecoderTrap, Encoding};
+use crate::{ByteSet, LanguagePack};
+use encoding::all::{ASCII, ISO_8859_1, ISO_8859_2, UTF_16BE, UTF_16LE, WINDOWS_1250};
+use encoding::{DecoderTrap, EncoderTrap, EncodingRef};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
 use std::str;
 
 #[cfg(not(target_arch = "wasm32"))]
 use clap::ValueEnum;
 
+/// Characters of DOS code page 852 from byte 128, lower bytes are ASCII.
+const CP852: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'ů', 'ć', 'ç', 'ł', 'ë', 'Ő', 'ő', 'î', 'Ź', 'Ä', 'Ć', //
+    'É', 'Ĺ', 'ĺ', 'ô', 'ö', 'Ľ', 'ľ', 'Ś', 'ś', 'Ö', 'Ü', 'Ť', 'ť', 'Ł', '×', 'č', //
+    'á', 'í', 'ó', 'ú', 'Ą', 'ą', 'Ž', 'ž', 'Ę', 'ę', '¬', 'ź', 'Č', 'ş', '«', '»', //
+    '░', '▒', '▓', '│', '┤', 'Á', 'Â', 'Ě', 'Ş', '╣', '║', '╗', '╝', 'Ż', 'ż', '┐', //
+    '└', '┴', '┬', '├', '─', '┼', 'Ă', 'ă', '╚', '╔', '╩', '╦', '╠', '═', '╬', '¤', //
+    'đ', 'Đ', 'Ď', 'Ë', 'ď', 'Ň', 'Í', 'Î', 'ě', '┘', '┌', '█', '▄', 'Ţ', 'Ů', '▀', //
+    'Ó', 'ß', 'Ô', 'Ń', 'ń', 'ň', 'Š', 'š', 'Ŕ', 'Ú', 'ŕ', 'Ű', 'ý', 'Ý', 'ţ', '´', //
+    '\u{ad}', '˝', '˛', 'ˇ', '˘', '§', '÷', '¸', '°', '¨', '˙', 'ű', 'Ř', 'ř', '■', '\u{a0}',
+];
+
+/// Text decoded with replacement characters in place of bytes that can not be decoded.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LossyText {
+    pub text: String,
+    /// Byte ranges replaced by `char::REPLACEMENT_CHARACTER`, in order of the text.
+    pub errors: Vec<Range<usize>>,
+}
+
+impl LossyText {
+    /// Key positions of bytes that failed to decode, sorted.
+    pub fn error_positions(&self, key_length: usize) -> Vec<usize> {
+        let mut positions: Vec<usize> = self
+            .errors
+            .iter()
+            .flat_map(Clone::clone)
+            .map(|offset| offset % key_length.max(1))
+            .collect();
+        positions.sort_unstable();
+        positions.dedup();
+        positions
+    }
+
+    fn push_error(&mut self, range: Range<usize>) {
+        self.text.push(char::REPLACEMENT_CHARACTER);
+        self.errors.push(range);
+    }
+}
+
+/// A character decoded from a range of bytes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedCharacter {
+    /// Decoded character, `char::REPLACEMENT_CHARACTER` if the bytes are not valid.
+    pub character: char,
+    pub bytes: Range<usize>,
+    pub valid: bool,
+}
+
+impl DecodedCharacter {
+    fn new(character: Option<char>, bytes: Range<usize>) -> Self {
+        Self {
+            character: character.unwrap_or(char::REPLACEMENT_CHARACTER),
+            bytes,
+            valid: character.is_some(),
+        }
+    }
+
+    /// Key positions the character was encrypted with, in order of its bytes.
+    pub fn key_positions(&self, key_length: usize) -> Vec<usize> {
+        self.bytes
+            .clone()
+            .map(|offset| offset % key_length.max(1))
+            .collect()
+    }
+}
+
 /// Text encoding.
 #[cfg(not(target_arch = "wasm32"))]
-#[derive(PartialEq, ValueEnum, Clone, Debug)]
+#[derive(PartialEq, ValueEnum, Clone, Debug, Default, Serialize, Deserialize)]
 pub enum TextEncoding {
+    #[default]
     WINDOWS1250,
     UTF8,
+    #[value(name = "iso-8859-2")]
+    ISO88592,
+    CP852,
+    LATIN1,
+    ASCII,
+    #[value(name = "utf16le")]
+    UTF16LE,
+    #[value(name = "utf16be")]
+    UTF16BE,
 }
 
 #[cfg(target_arch = "wasm32")]
-#[derive(PartialEq, Clone, Debug)]
+#[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
 pub enum TextEncoding {
+    #[default]
     WINDOWS1250,
     UTF8,
+    ISO88592,
+    CP852,
+    LATIN1,
+    ASCII,
+    UTF16LE,
+    UTF16BE,
 }
 
 impl TextEncoding {
+    /// Every supported encoding.
+    pub const ALL: [TextEncoding; 8] = [
+        TextEncoding::WINDOWS1250,
+        TextEncoding::UTF8,
+        TextEncoding::ISO88592,
+        TextEncoding::CP852,
+        TextEncoding::LATIN1,
+        TextEncoding::ASCII,
+        TextEncoding::UTF16LE,
+        TextEncoding::UTF16BE,
+    ];
+
     /// Decode bytes to string.
     pub fn decode(&self, bytes: &[u8]) -> Option<String> {
         match self {
-            TextEncoding::WINDOWS1250 => WINDOWS_1250.decode(bytes, DecoderTrap::Strict).ok(),
             TextEncoding::UTF8 => str::from_utf8(bytes).ok().map(ToString::to_string),
+            TextEncoding::CP852 => Some(bytes.iter().map(|byte| cp852_decode(*byte)).collect()),
+            encoding => encoding
+                .codec()
+                .and_then(|codec| codec.decode(bytes, DecoderTrap::Strict).ok()),
         }
     }
 
-    /// Get alphabet.
-    pub fn alphabet(&self) -> HashSet<u8> {
+    /// Decode bytes to string, replacing bytes that can not be decoded.
+    pub fn decode_lossy(&self, bytes: &[u8]) -> LossyText {
+        let mut decoded = LossyText::default();
+        for character in self.decode_characters(bytes) {
+            if character.valid {
+                decoded.text.push(character.character);
+            } else {
+                decoded.push_error(character.bytes);
+            }
+        }
+        decoded
+    }
+
+    /// Decode bytes to characters, each with the range of bytes it was decoded from.
+    /// Bytes that can not be decoded become invalid replacement characters.
+    pub fn decode_characters(&self, bytes: &[u8]) -> Vec<DecodedCharacter> {
         match self {
-            TextEncoding::WINDOWS1250 => crate::windows1250_alphabet(),
-            TextEncoding::UTF8 => crate::utf8_alphabet(),
+            TextEncoding::UTF8 => decode_utf8(bytes),
+            TextEncoding::UTF16LE => decode_utf16(bytes, u16::from_le_bytes),
+            TextEncoding::UTF16BE => decode_utf16(bytes, u16::from_be_bytes),
+            encoding => {
+                let table = encoding.single_byte_table();
+                bytes
+                    .iter()
+                    .enumerate()
+                    .map(|(offset, byte)| {
+                        DecodedCharacter::new(table[*byte as usize], offset..offset + 1)
+                    })
+                    .collect()
+            }
         }
     }
 
+    /// Encode string to bytes.
+    pub fn encode(&self, text: &str) -> Option<Vec<u8>> {
+        match self {
+            TextEncoding::UTF8 => Some(text.as_bytes().to_vec()),
+            TextEncoding::CP852 => text.chars().map(cp852_encode).collect(),
+            encoding => encoding
+                .codec()
+                .and_then(|codec| codec.encode(text, EncoderTrap::Strict).ok()),
+        }
+    }
+
+    /// Get alphabet.
+    pub fn alphabet(&self) -> ByteSet {
+        LanguagePack::polish().alphabet(self)
+    }
+
     /// Get most popular words.
     pub fn words(&self) -> Vec<Vec<u8>> {
+        LanguagePack::polish().words(self)
+    }
+
+    /// Codec of the `encoding` crate, none for encodings implemented here.
+    fn codec(&self) -> Option<EncodingRef> {
         match self {
-            TextEncoding::WINDOWS1250 => crate::windows1250_words(),
-            TextEncoding::UTF8 => crate::utf8_words(),
+            TextEncoding::WINDOWS1250 => Some(WINDOWS_1250),
+            TextEncoding::ISO88592 => Some(ISO_8859_2),
+            TextEncoding::LATIN1 => Some(ISO_8859_1),
+            TextEncoding::ASCII => Some(ASCII),
+            TextEncoding::UTF16LE => Some(UTF_16LE),
+            TextEncoding::UTF16BE => Some(UTF_16BE),
+            TextEncoding::UTF8 | TextEncoding::CP852 => None,
         }
     }
+
+    /// Character of every byte in a single byte encoding, none for undefined bytes.
+    fn single_byte_table(&self) -> [Option<char>; 256] {
+        let mut table = [None; 256];
+        for (byte, character) in table.iter_mut().enumerate() {
+            *character = match self {
+                TextEncoding::CP852 => Some(cp852_decode(byte as u8)),
+                encoding => encoding
+                    .codec()
+                    .and_then(|codec| codec.decode(&[byte as u8], DecoderTrap::Strict).ok())
+                    .and_then(|text| text.chars().next()),
+            };
+        }
+        table
+    }
+}
+
+fn decode_utf8(bytes: &[u8]) -> Vec<DecodedCharacter> {
+    let mut characters = Vec::with_capacity(bytes.len());
+    let mut offset = 0;
+
+    while offset < bytes.len() {
+        let (valid, end) = match str::from_utf8(&bytes[offset..]) {
+            Ok(_) => (bytes.len(), bytes.len()),
+            Err(error) => {
+                let valid = offset + error.valid_up_to();
+                let end = error
+                    .error_len()
+                    .map_or(bytes.len(), |length| valid + length);
+                (valid, end)
+            }
+        };
+
+        let text = str::from_utf8(&bytes[offset..valid]).unwrap_or_default();
+        characters.extend(text.char_indices().map(|(index, character)| {
+            let start = offset + index;
+            DecodedCharacter::new(Some(character), start..start + character.len_utf8())
+        }));
+        if valid < end {
+            characters.push(DecodedCharacter::new(None, valid..end));
+        }
+        offset = end;
+    }
+
+    characters
+}
+
+fn decode_utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16) -> Vec<DecodedCharacter> {
+    let units = bytes.chunks_exact(2).map(|pair| unit([pair[0], pair[1]]));
+    let mut characters = Vec::with_capacity(bytes.len() / 2);
+    let mut offset = 0;
+
+    for character in char::decode_utf16(units) {
+        let character = character.ok();
+        let length = character.map_or(2, |character| character.len_utf16() * 2);
+        characters.push(DecodedCharacter::new(character, offset..offset + length));
+        offset += length;
+    }
+    if offset < bytes.len() {
+        characters.push(DecodedCharacter::new(None, offset..bytes.len()));
+    }
+
+    characters
 }
 
-impl Default for TextEncoding {
-    fn default() -> Self {
-        Self::WINDOWS1250
+fn cp852_decode(byte: u8) -> char {
+    match byte {
+        0..=127 => byte as char,
+        _ => CP852[byte as usize - 128],
     }
 }
 
+fn cp852_encode(character: char) -> Option<u8> {
+    if character.is_ascii() {
+        return Some(character as u8);
+    }
+    CP852
+        .iter()
+        .position(|value| *value == character)
+        .map(|index| (index + 128) as u8)
+}
+
 impl std::fmt::Display for TextEncoding {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             TextEncoding::WINDOWS1250 => write!(f, "Windows-1250"),
             TextEncoding::UTF8 => write!(f, "UTF-8"),
+            TextEncoding::ISO88592 => write!(f, "ISO-8859-2"),
+            TextEncoding::CP852 => write!(f, "CP852"),
+            TextEncoding::LATIN1 => write!(f, "Latin-1"),
+            TextEncoding::ASCII => write!(f, "ASCII"),
+            TextEncoding::UTF16LE => write!(f, "UTF-16LE"),
+            TextEncoding::UTF16BE => write!(f, "UTF-16BE"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INVALID: char = char::REPLACEMENT_CHARACTER;
+
+    fn decoded(encoding: TextEncoding, bytes: &[u8]) -> Vec<(char, Range<usize>, bool)> {
+        encoding
+            .decode_characters(bytes)
+            .into_iter()
+            .map(|character| (character.character, character.bytes, character.valid))
+            .collect()
+    }
+
+    #[test]
+    fn utf8_characters_keep_their_bytes() {
+        assert_eq!(
+            decoded(TextEncoding::UTF8, "zą€😀".as_bytes()),
+            vec![
+                ('z', 0..1, true),
+                ('ą', 1..3, true),
+                ('€', 3..6, true),
+                ('😀', 6..10, true),
+            ]
+        );
+        assert_eq!(
+            decoded(TextEncoding::UTF8, &[b'a', 0xC4, b'b', 0xFF, 0xE2, 0x82]),
+            vec![
+                ('a', 0..1, true),
+                (INVALID, 1..2, false),
+                ('b', 2..3, true),
+                (INVALID, 3..4, false),
+                (INVALID, 4..6, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn utf16_characters_keep_their_bytes() {
+        assert_eq!(
+            decoded(
+                TextEncoding::UTF16LE,
+                &[0x61, 0x00, 0x3D, 0xD8, 0x00, 0xDE, 0x41]
+            ),
+            vec![
+                ('a', 0..2, true),
+                ('😀', 2..6, true),
+                (INVALID, 6..7, false),
+            ]
+        );
+        assert_eq!(
+            decoded(TextEncoding::UTF16BE, &[0xD8, 0x3D, 0xDE, 0x00, 0x00, 0x61]),
+            vec![('😀', 0..4, true), ('a', 4..6, true)]
+        );
+        assert_eq!(
+            decoded(TextEncoding::UTF16LE, &[0x3D, 0xD8, 0x61, 0x00]),
+            vec![(INVALID, 0..2, false), ('a', 2..4, true)]
+        );
+    }
+
+    #[test]
+    fn cp852_decodes_polish_letters() {
+        let letters = "ąćęłńóśźżĄĆĘŁŃÓŚŹŻ";
+        let bytes = [
+            0xA5, 0x86, 0xA9, 0x88, 0xE4, 0xA2, 0x98, 0xAB, 0xBE, 0xA4, 0x8F, 0xA8, 0x9D, 0xE3,
+            0xE0, 0x97, 0x8D, 0xBD,
+        ];
+        let characters = decoded(TextEncoding::CP852, &bytes);
+        assert_eq!(
+            characters
+                .iter()
+                .map(|(character, _, _)| character)
+                .collect::<String>(),
+            letters
+        );
+        for (offset, (_, range, valid)) in characters.into_iter().enumerate() {
+            assert_eq!(range, offset..offset + 1);
+            assert!(valid);
         }
+        assert_eq!(TextEncoding::CP852.encode(letters), Some(bytes.to_vec()));
     }
 }
@@ -1,26 +1,95 @@
-use super::NonZeroUsizeInput;
-use crate::{Cipher, Cracker, PotentialKey, TextEncoding};
-use eframe::{App, Frame};
+use super::grid_view::Cell;
+use super::{CrackRequest, CrackTask, GridView, HexView, NonZeroUsizeInput};
+use crate::{
+    Alphabet, AlphabetError, Cipher, CribPlacement, DecodedCharacter, KeyLengthCandidate,
+    KeyLengthEstimator, LanguagePack, PotentialKey, Session, SessionCiphertext, SessionError,
+    TextEncoding, ALPHABET_CLASSES, ALPHABET_EXTENSION, LANGUAGE_EXTENSION, SESSION_EXTENSION,
+};
+use eframe::{App, CreationContext, Frame};
 use egui::scroll_area::ScrollArea;
 use egui::text::LayoutJob;
 use egui::{
-    Align, CentralPanel, Color32, ComboBox, Context, DroppedFile, Hyperlink, Layout, RichText,
-    TopBottomPanel, Ui, Window,
+    Align, CentralPanel, Color32, ComboBox, Context, DroppedFile, Galley, Hyperlink, Key, Label,
+    Layout, Modifiers, Pos2, ProgressBar, RichText, Sense, TextEdit, TopBottomPanel, Ui, Window,
 };
 use std::num::NonZeroUsize;
+use std::ops::Range;
+
+/// Number of estimated key lengths shown to the user.
+const SUGGESTED_KEY_LENGTHS: usize = 5;
+
+/// Number of key candidates shown in one row, each with its score.
+const POSSIBILITIES_PER_ROW: usize = 8;
+
+/// Maximum number of crib placements and their echoes shown to the user.
+const SHOWN_CRIB_PLACEMENTS: usize = 100;
+
+/// Background of a character with only some of its key positions selected.
+const PARTIAL_SELECTION: Color32 = Color32::from_rgb(0, 0, 80);
+
+/// Maximum number of key positions listed as undecodable.
+const SHOWN_UNDECODABLE_POSITIONS: usize = 20;
+
+/// How the decrypted content is shown.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ContentView {
+    /// Running text.
+    Text,
+    /// One column per key position.
+    Grid,
+}
 
 pub struct Application {
     file: Option<DroppedFile>,
     message: Option<LayoutJob>,
+    error: Option<String>,
+    content_view: ContentView,
+    plaintext: Vec<u8>,
+    characters: Vec<DecodedCharacter>,
+    /// Cell of every plaintext byte shown by the grid and hex views.
+    cells: Vec<Cell>,
+    undecodable: Vec<usize>,
+    edited_character: Option<usize>,
+    replacement: String,
+    replacement_error: Option<&'static str>,
     key: Option<PotentialKey>,
     selected_key: Option<usize>,
+    hex_scroll: Option<usize>,
     encoding: TextEncoding,
+    detect_encoding: bool,
+    languages: Vec<LanguagePack>,
+    language: usize,
+    language_message: Option<String>,
+    alphabet: String,
+    alphabet_message: Option<String>,
     key_length: NonZeroUsizeInput,
-    cracker: Cracker,
+    key_lengths: Vec<KeyLengthCandidate>,
+    crack_task: Option<CrackTask>,
     cipher: Cipher,
+    crib: String,
+    crib_placements: Vec<CribPlacement>,
+    selected_placement: Option<usize>,
+    #[cfg(not(target_arch = "wasm32"))]
+    session_path: String,
+    session_message: Option<String>,
 }
 
 impl Application {
+    /// Create the application, restoring the session saved by the previous run.
+    pub fn new(context: &CreationContext<'_>) -> Self {
+        let mut application = Self::default();
+        if let Some(session) = context
+            .storage
+            .and_then(|storage| storage.get_string(eframe::APP_KEY))
+            .and_then(|text| Session::load(&text).ok())
+        {
+            if let Err(error) = application.open_session(session) {
+                application.session_message = Some(error.to_string());
+            }
+        }
+        application
+    }
+
     fn create_controls(&mut self, ui: &mut Ui) {
         ui.horizontal(|ui| {
             ui.label("Key length: ");
@@ -29,42 +98,96 @@ impl Application {
                 self.refresh_content();
             }
         });
+        if !self.key_lengths.is_empty() {
+            let mut selected_length = None;
+            ui.horizontal(|ui| {
+                ui.label("Suggested: ");
+                for candidate in self.key_lengths.iter().take(SUGGESTED_KEY_LENGTHS) {
+                    let text = format!("{} ({:.2})", candidate.length, candidate.score);
+                    if ui.button(text).clicked() {
+                        selected_length = Some(candidate.length);
+                    }
+                }
+            });
+            if let Some(length) = selected_length {
+                self.key_length = length.into();
+                self.cipher = Cipher::default();
+                self.refresh_content();
+            }
+        }
         ui.horizontal(|ui| {
             ui.label("Encoding: ");
             ComboBox::from_id_source("Encoding")
                 .selected_text(self.encoding.to_string())
                 .show_ui(ui, |ui| {
-                    let mut changed = ui
-                        .selectable_value(
-                            &mut self.encoding,
-                            TextEncoding::UTF8,
-                            TextEncoding::UTF8.to_string(),
-                        )
-                        .changed();
-                    changed |= ui
-                        .selectable_value(
-                            &mut self.encoding,
-                            TextEncoding::WINDOWS1250,
-                            TextEncoding::WINDOWS1250.to_string(),
-                        )
-                        .changed();
+                    let mut changed = false;
+                    for encoding in TextEncoding::ALL {
+                        let text = encoding.to_string();
+                        changed |= ui
+                            .selectable_value(&mut self.encoding, encoding, text)
+                            .changed();
+                    }
                     if changed {
-                        self.cracker = Cracker::new(&self.encoding);
+                        self.alphabet_message = self.check_alphabet();
+                        self.refresh_content();
+                    }
+                });
+            ui.checkbox(&mut self.detect_encoding, "Detect")
+                .on_hover_text("Crack under every encoding and select the most probable one");
+            ui.label("Language: ");
+            ComboBox::from_id_source("Language")
+                .selected_text(self.languages[self.language].to_string())
+                .show_ui(ui, |ui| {
+                    for (index, language) in self.languages.iter().enumerate() {
+                        ui.selectable_value(&mut self.language, index, language.to_string());
                     }
                 });
         });
+        ui.label(format!(
+            "Drop a .{} file to add a language.",
+            LANGUAGE_EXTENSION
+        ));
+        if let Some(message) = &self.language_message {
+            ui.label(message);
+        }
+        self.create_alphabet_controls(ui);
 
-        if ui.button("Crack").clicked() && self.file.is_some() {
-            let key_option = self
-                .file
-                .as_ref()
-                .and_then(|file| file.bytes.as_ref())
-                .map(|bytes| self.cracker.crack(bytes, *self.key_length));
-            if let Some(key) = key_option {
-                self.cipher = Cipher::new(key.get_current_key());
-                self.key = Some(key);
+        ui.separator();
+        self.create_session_controls(ui);
+        ui.separator();
+
+        if let Some(task) = &self.crack_task {
+            let (phase, progress) = task.progress();
+            ui.horizontal(|ui| {
+                ui.label(format!("{}: ", phase));
+                ui.add(ProgressBar::new(progress).show_percentage());
+            });
+            if ui.button("Cancel").clicked() {
+                if let Some(task) = self.crack_task.take() {
+                    task.cancel();
+                }
+            }
+        } else if ui.button("Crack").clicked() {
+            let alphabet = self.alphabet();
+            if let Err(error) = &alphabet {
+                self.alphabet_message = Some(error.to_string());
+            }
+            if let (Some(bytes), Ok(alphabet)) = (
+                self.file.as_ref().and_then(|file| file.bytes.as_ref()),
+                alphabet,
+            ) {
+                let request = CrackRequest {
+                    encoding: (!self.detect_encoding).then(|| self.encoding.clone()),
+                    language: self.languages[self.language].clone(),
+                    alphabet,
+                    key_length: *self.key_length,
+                    contents: bytes.to_vec(),
+                };
+                match CrackTask::spawn(ui.ctx(), request) {
+                    Ok(task) => self.crack_task = Some(task),
+                    Err(error) => self.error = Some(error),
+                }
             }
-            self.refresh_content();
         }
 
         if let Some(key) = &mut self.key {
@@ -73,13 +196,22 @@ impl Application {
 
             ui.separator();
 
+            if key.len() != self.key_length.get() {
+                ui.colored_label(
+                    Color32::YELLOW,
+                    format!(
+                        "The key has {} bytes, crack again to use the new key length.",
+                        key.len()
+                    ),
+                );
+            }
             ui.label("Key (click non-green value to correct it): ");
-            for index in 0..(self.key_length.get() / 16 + 1) {
+            for index in 0..(key.len() / 16 + 1) {
                 ui.horizontal(|ui| {
                     for i in 0..16 {
                         let index = index * 16 + i;
 
-                        if index >= self.key_length.get() {
+                        if index >= key.len() {
                             break;
                         }
 
@@ -96,48 +228,51 @@ impl Application {
                         if key.is_decoded(index) {
                             ui.label(text);
                         } else if ui.button(text).clicked() {
-                            self.selected_key = if let Some(prev) = self.selected_key {
-                                if prev == index {
-                                    None
-                                } else {
-                                    Some(index)
-                                }
-                            } else {
-                                Some(index)
-                            };
+                            self.selected_key = toggle_key(self.selected_key, index);
                             should_refresh = true;
                         }
                     }
                 });
             }
 
-            if let Some(index) = self.selected_key {
+            // Columns of the grid view follow the key length, which may be past the key.
+            if let Some(index) = self.selected_key.filter(|index| *index < key.len()) {
                 ui.separator();
                 ui.horizontal(|ui| {
                     ui.label(format!("Selected key: {:02X?}", current_key[index]));
                     if ui.button("Confirm value").clicked() {
-                        key.accept_value(index);
-                        self.selected_key = None;
+                        match key.accept_value(index) {
+                            Ok(()) => self.selected_key = None,
+                            Err(error) => self.error = Some(error.to_string()),
+                        }
                         should_refresh = true;
                     }
                 });
-                ui.label("Possibilities:");
+                ui.label("Possibilities (score, votes of dictionary words placed over them):");
 
                 let possibility = key.get_possibilities(index);
+                let scores = key.get_scores(index);
+                let votes = key.get_votes(index);
 
                 let mut new_value = None;
 
-                for i in 0..(possibility.len() / 16 + 1) {
+                for i in 0..(possibility.len() / POSSIBILITIES_PER_ROW + 1) {
                     ui.horizontal(|ui| {
-                        for j in 0..16 {
-                            let possibility_index = i * 16 + j;
+                        for j in 0..POSSIBILITIES_PER_ROW {
+                            let possibility_index = i * POSSIBILITIES_PER_ROW + j;
 
                             if possibility_index >= possibility.len() {
                                 break;
                             }
 
-                            let mut text =
-                                RichText::new(format!("{:02X?}", possibility[possibility_index]));
+                            let mut label = format!(
+                                "{:02X?} ({:.2}",
+                                possibility[possibility_index], scores[possibility_index]
+                            );
+                            if votes[possibility_index] > 0.0 {
+                                label += &format!(", {:.0} votes", votes[possibility_index]);
+                            }
+                            let mut text = RichText::new(label + ")");
 
                             if possibility[possibility_index] == current_key[index] {
                                 text = text.color(Color32::LIGHT_GREEN);
@@ -151,20 +286,351 @@ impl Application {
                 }
 
                 if let Some(new_value) = new_value {
-                    key.set_value(index, new_value);
+                    if let Err(error) = key.set_value(index, new_value) {
+                        self.error = Some(error.to_string());
+                    }
                     should_refresh = true;
                 }
             }
 
             if should_refresh {
-                self.cipher = Cipher::new(key.get_current_key());
-                self.refresh_content();
+                self.refresh_key();
+            }
+        }
+    }
+
+    /// Show the key once background cracking is finished.
+    fn poll_crack_task(&mut self) {
+        let Some(result) = self.crack_task.as_mut().and_then(CrackTask::take_result) else {
+            return;
+        };
+        self.crack_task = None;
+        let (encoding, key) = match result {
+            Ok(result) => result,
+            Err(error) => {
+                self.error = Some(error);
+                return;
+            }
+        };
+        self.encoding = encoding;
+        self.alphabet_message = self.check_alphabet();
+        if let Some(key_length) = NonZeroUsize::new(key.len()) {
+            self.key_length = key_length.into();
+        }
+        self.key = Some(key);
+        self.selected_key = None;
+        self.crib_placements.clear();
+        self.selected_placement = None;
+        self.edited_character = None;
+        self.refresh_key();
+    }
+
+    fn create_session_controls(&mut self, ui: &mut Ui) {
+        #[cfg(not(target_arch = "wasm32"))]
+        ui.horizontal(|ui| {
+            ui.label("Session: ");
+            ui.text_edit_singleline(&mut self.session_path);
+            if ui.button("Save session").clicked() {
+                let result = self
+                    .session()
+                    .ok_or_else(|| SessionError::Format("Nothing to save".to_owned()))
+                    .and_then(|session| session.save())
+                    .and_then(|text| {
+                        std::fs::write(&self.session_path, text).map_err(SessionError::Io)
+                    });
+                self.session_message = Some(match result {
+                    Ok(()) => "Session saved".to_owned(),
+                    Err(error) => error.to_string(),
+                });
+            }
+            if ui.button("Open session").clicked() {
+                let result = std::fs::read_to_string(&self.session_path)
+                    .map_err(SessionError::Io)
+                    .and_then(|text| Session::load(&text))
+                    .and_then(|session| self.open_session(session));
+                self.session_message = result.err().map(|error| error.to_string());
+            }
+        });
+
+        ui.label(format!(
+            "Drop a .{} file to open a session.",
+            SESSION_EXTENSION
+        ));
+
+        if let Some(message) = &self.session_message {
+            ui.label(message);
+        }
+    }
+
+    /// Create a session from the current state if there is anything to save.
+    /// A file opened from disk is referenced by its path, others are embedded.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn session(&self) -> Option<Session> {
+        let file = self.file.as_ref()?;
+        let bytes = file.bytes.as_ref()?;
+        let key = self.key.as_ref()?;
+        let ciphertext = match &file.path {
+            Some(path) => SessionCiphertext::File {
+                path: path.display().to_string(),
+                hash: crate::hash_bytes(bytes),
+            },
+            None => SessionCiphertext::Embedded {
+                name: file.name.clone(),
+                bytes: bytes.to_vec(),
+            },
+        };
+        Some(Session::new(
+            ciphertext,
+            self.encoding.clone(),
+            *self.key_length,
+            key.clone(),
+        ))
+    }
+
+    fn open_session(&mut self, session: Session) -> Result<(), SessionError> {
+        let bytes = session.read_ciphertext()?;
+        let (name, path) = match session.ciphertext {
+            SessionCiphertext::Embedded { name, .. } => (name, None),
+            SessionCiphertext::File { path, .. } => (path.clone(), Some(path.into())),
+        };
+
+        self.open_file(DroppedFile {
+            name,
+            path,
+            bytes: Some(bytes.into()),
+            ..Default::default()
+        });
+        self.encoding = session.encoding;
+        self.key_length = session.key_length.into();
+        self.key = Some(session.key);
+        self.refresh_key();
+        Ok(())
+    }
+
+    fn open_file(&mut self, file: DroppedFile) {
+        if let Some(task) = self.crack_task.take() {
+            task.cancel();
+        }
+        self.key = None;
+        self.selected_key = None;
+        self.crib_placements.clear();
+        self.selected_placement = None;
+        self.edited_character = None;
+        self.cipher = Cipher::default();
+        self.key_lengths = file
+            .bytes
+            .as_ref()
+            .map(|bytes| KeyLengthEstimator::default().estimate(bytes))
+            .unwrap_or_default();
+        if let Some(candidate) = self.key_lengths.first() {
+            self.key_length = candidate.length.into();
+        }
+        self.file = Some(file);
+        self.refresh_content();
+    }
+
+    fn create_alphabet_controls(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Alphabet: ");
+            let edit = TextEdit::singleline(&mut self.alphabet).hint_text("Language alphabet");
+            if ui.add(edit).changed() {
+                self.alphabet_message = self.check_alphabet();
+            }
+            if ui.button("Edit language alphabet").clicked() {
+                self.alphabet = escape_alphabet(&self.languages[self.language].alphabet);
+                self.alphabet_message = self.check_alphabet();
+            }
+        });
+        ui.label(format!(
+            "Classes: {}. Drop a .{} file to load an alphabet.",
+            ALPHABET_CLASSES
+                .map(|class| format!("[:{class}:]"))
+                .join(" "),
+            ALPHABET_EXTENSION
+        ));
+        if let Some(message) = &self.alphabet_message {
+            ui.label(message);
+        }
+    }
+
+    /// Alphabet typed by the user, none if the language alphabet is used.
+    fn alphabet(&self) -> Result<Option<Alphabet>, AlphabetError> {
+        if self.alphabet.is_empty() {
+            Ok(None)
+        } else {
+            Alphabet::parse(&self.alphabet).map(Some)
+        }
+    }
+
+    /// Describe problems with the typed alphabet, if there are any.
+    fn check_alphabet(&self) -> Option<String> {
+        match self.alphabet() {
+            Ok(Some(alphabet)) => {
+                let unencodable = alphabet.unencodable(&self.encoding);
+                (!unencodable.is_empty()).then(|| {
+                    format!(
+                        "Not in {}, skipped: {}",
+                        self.encoding,
+                        unencodable.into_iter().collect::<String>()
+                    )
+                })
             }
+            Ok(None) => None,
+            Err(error) => Some(error.to_string()),
+        }
+    }
+
+    /// Add a language and select it, replacing a language with the same name.
+    fn add_language(&mut self, language: LanguagePack) {
+        match self
+            .languages
+            .iter()
+            .position(|existing| existing.name == language.name)
+        {
+            Some(index) => {
+                self.languages[index] = language;
+                self.language = index;
+            }
+            None => {
+                self.languages.push(language);
+                self.language = self.languages.len() - 1;
+            }
+        }
+    }
+
+    fn create_history(&mut self, ui: &mut Ui) {
+        let Some(key) = &mut self.key else {
+            return;
+        };
+        let mut target = None;
+
+        ui.horizontal(|ui| {
+            let applied = key.history().len();
+            if ui.button("Undo").clicked() && applied > 0 {
+                target = Some(applied - 1);
+            }
+            if ui.button("Redo").clicked() && !key.undone().is_empty() {
+                target = Some(applied + 1);
+            }
+        });
+
+        ScrollArea::vertical()
+            .id_source("History")
+            .max_height(300.0)
+            .show(ui, |ui| {
+                let applied = key.history().len();
+                if ui.selectable_label(applied == 0, "Cracked key").clicked() {
+                    target = Some(0);
+                }
+                for (index, edit) in key
+                    .history()
+                    .iter()
+                    .chain(key.undone().iter().rev())
+                    .enumerate()
+                {
+                    let mut text = RichText::new(edit.to_string());
+                    if index >= applied {
+                        text = text.weak();
+                    }
+                    if ui.selectable_label(index + 1 == applied, text).clicked() {
+                        target = Some(index + 1);
+                    }
+                }
+            });
+
+        if let Some(target) = target {
+            key.jump_to(target);
+            self.refresh_key();
+        }
+    }
+
+    fn create_crib_dragging(&mut self, ui: &mut Ui) {
+        let (Some(key), Some(bytes)) = (
+            &mut self.key,
+            self.file.as_ref().and_then(|file| file.bytes.as_ref()),
+        ) else {
+            return;
+        };
+        let mut should_refresh = false;
+
+        ui.horizontal(|ui| {
+            ui.label("Crib: ");
+            ui.text_edit_singleline(&mut self.crib);
+            if ui.button("Drag").clicked() {
+                self.crib_placements = self
+                    .encoding
+                    .encode(&self.crib)
+                    .map(|crib| key.drag_crib(bytes, &crib))
+                    .unwrap_or_default();
+                self.selected_placement = None;
+            }
+        });
+
+        ui.label(format!("Placements: {}", self.crib_placements.len()));
+
+        ScrollArea::vertical()
+            .id_source("Placements")
+            .max_height(200.0)
+            .show(ui, |ui| {
+                for (index, placement) in self
+                    .crib_placements
+                    .iter()
+                    .take(SHOWN_CRIB_PLACEMENTS)
+                    .enumerate()
+                {
+                    let text = format!(
+                        "Offset {}, key position {}, score {:.2}",
+                        placement.offset,
+                        placement.key_position(key.len()),
+                        placement.score
+                    );
+                    let selected = self.selected_placement == Some(index);
+                    if ui.selectable_label(selected, text).clicked() {
+                        self.selected_placement = if selected { None } else { Some(index) };
+                    }
+                }
+            });
+
+        if let Some(placement) = self
+            .selected_placement
+            .and_then(|index| self.crib_placements.get(index))
+        {
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("Same key positions elsewhere:");
+                if ui.button("Apply").clicked() {
+                    if let Err(error) = placement.apply(key) {
+                        self.error = Some(error.to_string());
+                    }
+                    should_refresh = true;
+                }
+            });
+
+            ScrollArea::vertical()
+                .id_source("Echoes")
+                .max_height(200.0)
+                .show(ui, |ui| {
+                    for (offset, echo) in placement
+                        .echoes(bytes, key.len())
+                        .into_iter()
+                        .take(SHOWN_CRIB_PLACEMENTS)
+                    {
+                        let text = self.encoding.decode_lossy(&echo).text;
+                        ui.label(format!("{}: {:?}", offset, text));
+                    }
+                });
+        }
+
+        if should_refresh {
+            self.refresh_key();
         }
     }
 
     fn create_content(&mut self, ui: &mut Ui) {
         ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.content_view, ContentView::Text, "Text");
+            ui.selectable_value(&mut self.content_view, ContentView::Grid, "Grid")
+                .on_hover_text("One column per key position, click a column to select it");
             ui.with_layout(Layout::bottom_up(Align::Center), |ui| {
                 if let Some(file) = &self.file {
                     ui.label(&file.name);
@@ -176,50 +642,250 @@ impl Application {
 
         ui.separator();
 
+        if let Some(error) = &self.error {
+            let mut dismissed = false;
+            ui.horizontal(|ui| {
+                ui.colored_label(Color32::LIGHT_RED, error);
+                dismissed = ui.button("Dismiss").clicked();
+            });
+            if dismissed {
+                self.error = None;
+            }
+            ui.separator();
+        }
+
+        if !self.undecodable.is_empty() {
+            let mut positions: Vec<String> = self
+                .undecodable
+                .iter()
+                .take(SHOWN_UNDECODABLE_POSITIONS)
+                .map(ToString::to_string)
+                .collect();
+            if self.undecodable.len() > SHOWN_UNDECODABLE_POSITIONS {
+                positions.push("…".to_owned());
+            }
+            ui.colored_label(
+                Color32::RED,
+                format!(
+                    "Undecodable bytes at key positions: {}",
+                    positions.join(", ")
+                ),
+            );
+            ui.separator();
+        }
+
+        if let Some(character) = self
+            .edited_character
+            .and_then(|index| self.characters.get(index))
+            .cloned()
+        {
+            let range = character.bytes.clone();
+            ui.horizontal(|ui| {
+                let positions: Vec<String> = character
+                    .key_positions(self.key_length.get())
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect();
+                ui.label(format!(
+                    "Character at bytes {}..{}, key positions {}: ",
+                    range.start,
+                    range.end,
+                    positions.join(", ")
+                ));
+                ui.text_edit_singleline(&mut self.replacement);
+                if ui.button("Apply").clicked() {
+                    match self.replace_character(range.clone()) {
+                        Ok(()) => {
+                            self.edited_character = None;
+                            self.replacement_error = None;
+                        }
+                        Err(error) => self.replacement_error = Some(error),
+                    }
+                }
+                if ui.button("Cancel").clicked() {
+                    self.edited_character = None;
+                    self.replacement_error = None;
+                }
+            });
+            if let Some(error) = self.replacement_error {
+                ui.colored_label(Color32::LIGHT_RED, error);
+            }
+            ui.separator();
+        }
+
+        if self.content_view == ContentView::Grid {
+            let clicked_column = GridView::new(
+                &self.cells,
+                self.key.as_ref(),
+                self.key_length.get(),
+                self.selected_key,
+            )
+            .show(ui);
+            if let Some(column) = clicked_column {
+                self.selected_key = Some(column);
+                self.refresh_content();
+            }
+            return;
+        }
+
+        let mut clicked_character = None;
+
         ScrollArea::new([false, true]).show(ui, |ui| {
             if let Some(message) = &self.message {
-                ui.label(message.clone());
+                let (position, galley, response) = Label::new(message.clone())
+                    .sense(Sense::click())
+                    .layout_in_ui(ui);
+                if response.clicked() {
+                    clicked_character = response.interact_pointer_pos().and_then(|pointer| {
+                        character_at(galley.galley(), (pointer - position).to_pos2())
+                    });
+                }
+                galley.paint_with_visuals(ui.painter(), position, ui.style().noninteractive());
             }
         });
+
+        if let Some(index) = clicked_character {
+            if let (Some(key), Some(character)) = (&self.key, self.characters.get(index)) {
+                self.edited_character = Some(index);
+                self.replacement = if character.valid {
+                    character.character.to_string()
+                } else {
+                    String::new()
+                };
+                self.replacement_error = None;
+                self.selected_key = Some(character.bytes.start % key.len());
+                self.hex_scroll = Some(character.bytes.start);
+                self.refresh_content();
+            }
+        }
+    }
+
+    /// Replace the character decoded from the byte range by changing the key.
+    /// The new character must take the same bytes, so neighbouring characters stay intact.
+    fn replace_character(&mut self, bytes: Range<usize>) -> Result<(), &'static str> {
+        let mut characters = self.replacement.chars();
+        let (Some(character), None) = (characters.next(), characters.next()) else {
+            return Err("Type exactly one character");
+        };
+        let plain = self
+            .encoding
+            .encode(&character.to_string())
+            .ok_or("Character can not be encoded")?;
+        if plain.len() != bytes.len() {
+            return Err("Character must take as many bytes as the replaced one");
+        }
+        let contents = self
+            .file
+            .as_ref()
+            .and_then(|file| file.bytes.as_ref())
+            .ok_or("No file loaded")?;
+        let key = self.key.as_mut().ok_or("Crack the message first")?;
+        if plain.len() > key.len() {
+            return Err("Character is longer than the key");
+        }
+
+        let values = plain
+            .iter()
+            .enumerate()
+            .map(|(index, byte)| {
+                let offset = bytes.start + index;
+                contents
+                    .get(offset)
+                    .map(|value| (offset % key.len(), value ^ byte))
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or("Character does not fit in the message")?;
+
+        key.set_values(&values)
+            .map_err(|_| "Character is not possible at this position")?;
+
+        self.refresh_key();
+        Ok(())
+    }
+
+    /// Show ciphertext, key and plaintext bytes side by side.
+    fn create_hex_view(&mut self, ui: &mut Ui) {
+        let Some(ciphertext) = self.file.as_ref().and_then(|file| file.bytes.as_ref()) else {
+            return;
+        };
+        ui.label("Offset, ciphertext, key and plaintext (click a row to select its key byte):");
+        let clicked = HexView::new(ciphertext, &self.plaintext, self.cipher.key(), &self.cells)
+            .key(self.key.as_ref())
+            .selected(self.key_length.get(), self.selected_key)
+            .scroll_to(self.hex_scroll.take())
+            .show(ui);
+
+        if let Some(index) = clicked {
+            self.selected_key = toggle_key(self.selected_key, index);
+            self.refresh_content();
+        }
+    }
+
+    /// Decrypt the content with the current value of the key.
+    fn refresh_key(&mut self) {
+        if let Some(key) = &self.key {
+            match Cipher::new(key.get_current_key()) {
+                Ok(cipher) => self.cipher = cipher,
+                Err(error) => self.error = Some(error.to_string()),
+            }
+        }
+        self.refresh_content();
     }
 
     fn refresh_content(&mut self) {
-        let text_option = self
+        let key_length = self.key_length.get();
+        self.plaintext = self
             .file
             .as_ref()
             .and_then(|file| file.bytes.as_ref())
             .map(|bytes| self.cipher.decrypt(bytes))
-            .and_then(|bytes| self.encoding.decode(&bytes));
-        let mut job = LayoutJob::default();
+            .unwrap_or_default();
+        self.characters = self.encoding.decode_characters(&self.plaintext);
+        self.cells = Cell::from_characters(&self.characters);
 
-        if let Some(text) = text_option {
-            if let Some(key) = &mut self.key {
-                for (index, character) in text.chars().enumerate() {
-                    let index = index % self.key_length.get();
+        let mut undecodable: Vec<usize> = self
+            .characters
+            .iter()
+            .filter(|character| !character.valid)
+            .flat_map(|character| character.key_positions(key_length))
+            .collect();
+        undecodable.sort_unstable();
+        undecodable.dedup();
+        self.undecodable = undecodable;
 
-                    let mut text_format = egui::TextFormat::default();
+        let mut job = LayoutJob::default();
+        for character in &self.characters {
+            // A character spans several key positions in multibyte encodings.
+            let positions = character.key_positions(key_length);
 
-                    if key.is_decoded(index) {
-                        text_format.color = Color32::LIGHT_GREEN;
-                    } else if !key.is_uncertain(index) {
-                        text_format.color = Color32::YELLOW;
-                    } else if key.is_error(index) {
-                        text_format.color = Color32::LIGHT_RED;
-                    }
+            let mut text_format = egui::TextFormat::default();
 
-                    if let Some(highlighted) = self.selected_key {
-                        if highlighted == index {
-                            text_format.background = Color32::DARK_BLUE;
-                        }
-                    }
+            if !character.valid {
+                text_format.color = Color32::RED;
+            } else if let Some(key) = self
+                .key
+                .as_ref()
+                .filter(|key| positions.iter().all(|index| *index < key.len()))
+            {
+                if positions.iter().all(|index| key.is_decoded(*index)) {
+                    text_format.color = Color32::LIGHT_GREEN;
+                } else if positions.iter().all(|index| !key.is_uncertain(*index)) {
+                    text_format.color = Color32::YELLOW;
+                } else if positions.iter().any(|index| key.is_error(*index)) {
+                    text_format.color = Color32::LIGHT_RED;
+                }
+            }
 
-                    job.append(&character.to_string(), 0.0, text_format);
+            if let Some(highlighted) = self.selected_key {
+                if positions.iter().all(|index| *index == highlighted) {
+                    text_format.background = Color32::DARK_BLUE;
+                } else if positions.contains(&highlighted) {
+                    // Only one of the key positions of the character is selected.
+                    text_format.background = PARTIAL_SELECTION;
                 }
-            } else {
-                job.text = text;
             }
-        } else {
-            job.text = "Non decodable text".to_owned();
+
+            job.append(&character.character.to_string(), 0.0, text_format);
         }
         self.message = Some(job);
     }
@@ -231,20 +897,79 @@ impl Default for Application {
         Self {
             file: None,
             message: None,
+            error: None,
+            content_view: ContentView::Text,
+            plaintext: Vec::new(),
+            characters: Vec::new(),
+            cells: Vec::new(),
+            undecodable: Vec::new(),
+            edited_character: None,
+            replacement: String::new(),
+            replacement_error: None,
             key: None,
-            cracker: Cracker::new(&encoding),
             encoding,
+            detect_encoding: true,
+            languages: LanguagePack::built_in(),
+            language: 0,
+            language_message: None,
+            alphabet: String::new(),
+            alphabet_message: None,
             key_length: NonZeroUsize::new(256).unwrap().into(),
+            key_lengths: Vec::new(),
+            crib: String::new(),
+            crib_placements: Vec::new(),
+            selected_placement: None,
             selected_key: None,
+            hex_scroll: None,
             cipher: Cipher::default(),
+            crack_task: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            session_path: format!("crack.{}", SESSION_EXTENSION),
+            session_message: None,
         }
     }
 }
 
 impl App for Application {
+    /// Remember the session for the next run. Web storage is too small for ciphertexts.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        let text = self
+            .session()
+            .and_then(|session| session.save().ok())
+            .unwrap_or_default();
+        storage.set_string(eframe::APP_KEY, text);
+    }
+
     fn update(&mut self, ctx: &Context, _frame: &mut Frame) {
+        self.poll_crack_task();
+
         Window::new("Controls").show(ctx, |ui| self.create_controls(ui));
 
+        if self.key.is_some() {
+            Window::new("Crib dragging").show(ctx, |ui| self.create_crib_dragging(ui));
+            Window::new("History").show(ctx, |ui| self.create_history(ui));
+        }
+
+        if !ctx.wants_keyboard_input() {
+            let (undo, redo) = ctx.input_mut(|input| {
+                let redo = input.consume_key(Modifiers::COMMAND | Modifiers::SHIFT, Key::Z);
+                let undo = input.consume_key(Modifiers::COMMAND, Key::Z);
+                (undo, redo)
+            });
+            if let Some(key) = &mut self.key {
+                if (undo && key.undo()) | (redo && key.redo()) {
+                    self.refresh_key();
+                }
+            }
+        }
+
+        if self.file.is_some() {
+            Window::new("Hex view")
+                .default_height(300.0)
+                .show(ctx, |ui| self.create_hex_view(ui));
+        }
+
         TopBottomPanel::bottom("bottom_panel").show(ctx, |ui| {
             ui.with_layout(Layout::bottom_up(Align::Center), |ui| {
                 ui.add(Hyperlink::from_label_and_url(
@@ -256,11 +981,96 @@ impl App for Application {
 
         CentralPanel::default().show(ctx, |ui| self.create_content(ui));
 
-        ctx.input(|input| {
-            if let Some(file) = input.raw.dropped_files.first() {
-                self.file = Some(file.clone());
-                self.refresh_content();
+        if let Some(file) = ctx.input(|input| input.raw.dropped_files.first().cloned()) {
+            #[cfg(not(target_arch = "wasm32"))]
+            let file = read_dropped_file(file);
+
+            if file.name.ends_with(SESSION_EXTENSION) {
+                let result = file
+                    .bytes
+                    .as_ref()
+                    .ok_or_else(|| SessionError::Format("Empty file".to_owned()))
+                    .and_then(|bytes| Session::load(&String::from_utf8_lossy(bytes)))
+                    .and_then(|session| self.open_session(session));
+                self.session_message = result.err().map(|error| error.to_string());
+            } else if file.name.ends_with(LANGUAGE_EXTENSION) {
+                let result = file
+                    .bytes
+                    .as_ref()
+                    .map(|bytes| LanguagePack::load(&String::from_utf8_lossy(bytes)));
+                self.language_message = match result {
+                    Some(Ok(language)) => {
+                        let message = format!("Language {} added", language);
+                        self.add_language(language);
+                        Some(message)
+                    }
+                    Some(Err(error)) => Some(error.to_string()),
+                    None => Some("Empty file".to_owned()),
+                };
+            } else if file.name.ends_with(ALPHABET_EXTENSION) {
+                let text = file
+                    .bytes
+                    .as_ref()
+                    .map(|bytes| String::from_utf8_lossy(bytes).lines().collect::<String>())
+                    .unwrap_or_default();
+                self.alphabet = text;
+                self.alphabet_message = self.check_alphabet();
+            } else {
+                self.open_file(file);
             }
-        });
+        }
+    }
+}
+
+/// Select the key position, or deselect it if it is already selected.
+fn toggle_key(selected: Option<usize>, index: usize) -> Option<usize> {
+    if selected == Some(index) {
+        None
+    } else {
+        Some(index)
+    }
+}
+
+/// Index of the character whose glyph contains the point, none between glyphs.
+fn character_at(galley: &Galley, point: Pos2) -> Option<usize> {
+    let mut index = 0;
+    for row in &galley.rows {
+        let glyph = row
+            .glyphs
+            .iter()
+            .position(|glyph| glyph.logical_rect().contains(point));
+        if let Some(glyph) = glyph {
+            return Some(index + glyph);
+        }
+        index += row.char_count_including_newline();
+    }
+    None
+}
+
+/// Escape characters with a special meaning in alphabet expressions.
+fn escape_alphabet(characters: &str) -> String {
+    let mut escaped = String::new();
+    for character in characters.chars() {
+        match character {
+            '\\' | '[' => escaped.extend(['\\', character]),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            character => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+/// Native drops only carry the path, so read the contents from it.
+#[cfg(not(target_arch = "wasm32"))]
+fn read_dropped_file(mut file: DroppedFile) -> DroppedFile {
+    if file.bytes.is_none() {
+        file.bytes = file
+            .path
+            .as_ref()
+            .and_then(|path| std::fs::read(path).ok())
+            .map(Into::into);
     }
+    file
 }
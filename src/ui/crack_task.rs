@@ -0,0 +1,289 @@
+use crate::{
+    Alphabet, CrackPhase, Cracker, EncodingDetector, Error, LanguagePack, PotentialKey,
+    TextEncoding,
+};
+use egui::Context;
+use serde::{Deserialize, Serialize};
+use std::num::NonZeroUsize;
+
+/// Everything needed to crack a ciphertext away from the UI thread.
+#[derive(Serialize, Deserialize)]
+pub struct CrackRequest {
+    /// Encoding of the plaintext, detected if none.
+    pub encoding: Option<TextEncoding>,
+    pub language: LanguagePack,
+    /// Alphabet replacing the language alphabet.
+    pub alphabet: Option<Alphabet>,
+    pub key_length: NonZeroUsize,
+    pub contents: Vec<u8>,
+}
+
+/// Message sent by a running crack task.
+#[derive(Serialize, Deserialize)]
+pub enum CrackMessage {
+    Progress(CrackPhase, f32),
+    Finished(TextEncoding, PotentialKey),
+    /// Cracking failed with the given error message.
+    Failed(String),
+}
+
+/// Encoding and key found by a crack task, or the error it failed with.
+pub type CrackResult = Result<(TextEncoding, PotentialKey), String>;
+
+impl CrackRequest {
+    /// Crack the ciphertext, detecting the encoding if it is not given,
+    /// and send progress updates whenever a whole percent is done.
+    /// Fails with `Error::Cancelled` if `send` asked to cancel.
+    pub fn run(
+        &self,
+        send: &mut dyn FnMut(CrackMessage) -> bool,
+    ) -> Result<(TextEncoding, PotentialKey), Error> {
+        let mut last = None;
+        let mut progress = |phase, progress: f32| {
+            let percent = (phase, (progress * 100.0) as u32);
+            if last == Some(percent) {
+                return true;
+            }
+            last = Some(percent);
+            send(CrackMessage::Progress(phase, progress))
+        };
+
+        let Some(encoding) = &self.encoding else {
+            let detector = EncodingDetector::new(self.language.clone(), self.alphabet.clone());
+            let candidate = detector
+                .detect_with_progress(&self.contents, self.key_length, &mut progress)?
+                .into_iter()
+                .next()
+                .ok_or(Error::EmptyAlphabet)?;
+            return Ok((candidate.encoding, candidate.key));
+        };
+        let cracker = match &self.alphabet {
+            Some(alphabet) => Cracker::with_alphabet(encoding, &self.language, alphabet),
+            None => Cracker::with_language(encoding, &self.language),
+        };
+        let key = cracker.crack_with_progress(&self.contents, self.key_length, &mut progress)?;
+        Ok((encoding.clone(), key))
+    }
+
+    /// Run the request and send its result, nothing is sent if it was cancelled.
+    pub fn run_and_send(&self, send: &mut dyn FnMut(CrackMessage) -> bool) {
+        match self.run(send) {
+            Ok((encoding, key)) => send(CrackMessage::Finished(encoding, key)),
+            Err(Error::Cancelled) => true,
+            Err(error) => send(CrackMessage::Failed(error.to_string())),
+        };
+    }
+}
+
+/// Latest state of a crack task.
+struct TaskState {
+    phase: CrackPhase,
+    progress: f32,
+    result: Option<CrackResult>,
+}
+
+impl Default for TaskState {
+    fn default() -> Self {
+        Self {
+            phase: CrackPhase::XorAttack,
+            progress: 0.0,
+            result: None,
+        }
+    }
+}
+
+impl TaskState {
+    fn update(&mut self, message: CrackMessage) {
+        match message {
+            CrackMessage::Progress(phase, progress) => {
+                self.phase = phase;
+                self.progress = progress;
+            }
+            CrackMessage::Finished(encoding, key) => self.result = Some(Ok((encoding, key))),
+            CrackMessage::Failed(error) => self.result = Some(Err(error)),
+        }
+    }
+}
+
+/// Cracking running on a worker thread.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct CrackTask {
+    state: std::sync::Arc<std::sync::Mutex<TaskState>>,
+    cancelled: std::sync::Arc<std::sync::atomic::AtomicBool>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl CrackTask {
+    /// Start cracking, the context is repainted whenever progress changes.
+    /// Fails if the thread can not be started.
+    pub fn spawn(context: &Context, request: CrackRequest) -> Result<Self, String> {
+        use std::sync::atomic::Ordering;
+
+        let task = Self {
+            state: Default::default(),
+            cancelled: Default::default(),
+        };
+        let state = task.state.clone();
+        let cancelled = task.cancelled.clone();
+        let context = context.clone();
+
+        std::thread::Builder::new()
+            .spawn(move || {
+                let mut send = |message: CrackMessage| {
+                    if let Ok(mut state) = state.lock() {
+                        state.update(message);
+                    }
+                    context.request_repaint();
+                    !cancelled.load(Ordering::Relaxed)
+                };
+                request.run_and_send(&mut send);
+            })
+            .map_err(|error| format!("Failed to start cracking: {error}"))?;
+
+        Ok(task)
+    }
+
+    /// Stop cracking, the result is never delivered.
+    pub fn cancel(self) {
+        self.cancelled
+            .store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// Get the current phase and its progress.
+    pub fn progress(&self) -> (CrackPhase, f32) {
+        self.state
+            .lock()
+            .map(|state| (state.phase, state.progress))
+            .unwrap_or((CrackPhase::XorAttack, 0.0))
+    }
+
+    /// Take the encoding and the key, or the error, once cracking is finished.
+    pub fn take_result(&mut self) -> Option<CrackResult> {
+        self.state.lock().ok()?.result.take()
+    }
+}
+
+/// Script loading the worker binary, generated by Trunk.
+#[cfg(target_arch = "wasm32")]
+const WORKER_SCRIPT: &str = "./worker_loader.js";
+
+/// Cracking running in a web worker.
+#[cfg(target_arch = "wasm32")]
+pub struct CrackTask {
+    state: std::rc::Rc<std::cell::RefCell<TaskState>>,
+    worker: web_sys::Worker,
+    _on_message: wasm_bindgen::closure::Closure<dyn FnMut(web_sys::MessageEvent)>,
+    _on_error: wasm_bindgen::closure::Closure<dyn FnMut(web_sys::Event)>,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl CrackTask {
+    /// Start cracking, the context is repainted whenever progress changes.
+    /// Fails if the worker can not be started, a worker failing to load fails the task later.
+    pub fn spawn(context: &Context, request: CrackRequest) -> Result<Self, String> {
+        use wasm_bindgen::JsCast;
+
+        let text = ron::to_string(&request).map_err(|error| error.to_string())?;
+        let state: std::rc::Rc<std::cell::RefCell<TaskState>> = Default::default();
+        let worker = web_sys::Worker::new(WORKER_SCRIPT)
+            .map_err(|error| format!("Failed to start the cracking worker: {error:?}"))?;
+
+        let on_message = {
+            let state = state.clone();
+            let context = context.clone();
+            wasm_bindgen::closure::Closure::<dyn FnMut(web_sys::MessageEvent)>::new(
+                move |event: web_sys::MessageEvent| {
+                    let message = event
+                        .data()
+                        .as_string()
+                        .and_then(|text| ron::from_str(&text).ok());
+                    if let Some(message) = message {
+                        state.borrow_mut().update(message);
+                        context.request_repaint();
+                    }
+                },
+            )
+        };
+        worker.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+        let on_error = {
+            let state = state.clone();
+            let context = context.clone();
+            wasm_bindgen::closure::Closure::<dyn FnMut(web_sys::Event)>::new(
+                move |_: web_sys::Event| {
+                    state.borrow_mut().result = Some(Err(
+                        "The cracking worker failed to load or crashed".to_owned(),
+                    ));
+                    context.request_repaint();
+                },
+            )
+        };
+        worker.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+
+        let task = Self {
+            state,
+            worker,
+            _on_message: on_message,
+            _on_error: on_error,
+        };
+        if let Err(error) = task.worker.post_message(&text.into()) {
+            task.worker.terminate();
+            return Err(format!(
+                "Failed to send the ciphertext to the worker: {error:?}"
+            ));
+        }
+        Ok(task)
+    }
+
+    /// Stop cracking, the result is never delivered.
+    pub fn cancel(self) {
+        self.worker.terminate();
+    }
+
+    /// Get the current phase and its progress.
+    pub fn progress(&self) -> (CrackPhase, f32) {
+        let state = self.state.borrow();
+        (state.phase, state.progress)
+    }
+
+    /// Take the encoding and the key, or the error, once cracking is finished.
+    pub fn take_result(&mut self) -> Option<CrackResult> {
+        let result = self.state.borrow_mut().result.take();
+        if result.is_some() {
+            self.worker.terminate();
+        }
+        result
+    }
+}
+
+/// Entry point of the web worker, cracks every request it receives.
+#[cfg(target_arch = "wasm32")]
+pub fn worker_main() {
+    use wasm_bindgen::JsCast;
+
+    let scope: web_sys::DedicatedWorkerGlobalScope = js_sys::global().unchecked_into();
+    let on_message = {
+        let scope = scope.clone();
+        wasm_bindgen::closure::Closure::<dyn FnMut(web_sys::MessageEvent)>::new(
+            move |event: web_sys::MessageEvent| {
+                let request = event
+                    .data()
+                    .as_string()
+                    .and_then(|text| ron::from_str::<CrackRequest>(&text).ok());
+                let Some(request) = request else {
+                    return;
+                };
+
+                let mut send = |message: CrackMessage| {
+                    if let Ok(text) = ron::to_string(&message) {
+                        scope.post_message(&text.into()).ok();
+                    }
+                    true
+                };
+                request.run_and_send(&mut send);
+            },
+        )
+    };
+    scope.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+    on_message.forget();
+}
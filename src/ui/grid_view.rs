@@ -0,0 +1,166 @@
+use crate::{DecodedCharacter, PotentialKey};
+use egui::{Color32, Label, RichText, ScrollArea, Sense, TextStyle, Ui, Vec2};
+
+/// Width of a cell in monospace characters, enough for a hex byte or an escaped character.
+const CELL_CHARACTERS: f32 = 2.5;
+
+/// Plaintext laid out in rows of the key length, every column shares one key byte.
+pub struct GridView<'a> {
+    cells: &'a [Cell],
+    key: Option<&'a PotentialKey>,
+    key_length: usize,
+    selected: Option<usize>,
+}
+
+/// Contents of a single plaintext byte.
+#[derive(Clone)]
+pub(super) enum Cell {
+    /// First byte of a character.
+    Character(char),
+    /// Following byte of a multibyte character.
+    Continuation,
+    /// Byte that could not be decoded.
+    Invalid,
+}
+
+impl<'a> GridView<'a> {
+    pub(super) fn new(
+        cells: &'a [Cell],
+        key: Option<&'a PotentialKey>,
+        key_length: usize,
+        selected: Option<usize>,
+    ) -> Self {
+        Self {
+            cells,
+            key,
+            key_length: key_length.max(1),
+            selected,
+        }
+    }
+
+    /// Show the grid, returns the clicked column.
+    pub fn show(self, ui: &mut Ui) -> Option<usize> {
+        let cells = self.cells;
+
+        let font = TextStyle::Monospace.resolve(ui.style());
+        let glyph_width = ui.fonts(|fonts| fonts.glyph_width(&font, 'M'));
+        let row_height = ui.text_style_height(&TextStyle::Monospace);
+        let cell_size = Vec2::new(glyph_width * CELL_CHARACTERS, row_height);
+        let rows = (cells.len() + self.key_length - 1) / self.key_length;
+        let mut clicked = None;
+
+        ScrollArea::horizontal().show(ui, |ui| {
+            ui.spacing_mut().item_spacing.x = 0.0;
+
+            ui.horizontal(|ui| {
+                for position in 0..self.key_length {
+                    let (text, color) = self.header(position);
+                    let text = RichText::new(text)
+                        .monospace()
+                        .color(color)
+                        .background_color(self.background(position));
+                    let response = ui
+                        .add_sized(cell_size, Label::new(text).sense(Sense::click()))
+                        .on_hover_text(format!("Key position {}", position));
+                    if response.clicked() {
+                        clicked = Some(position);
+                    }
+                }
+            });
+            ui.separator();
+
+            ScrollArea::vertical().show_rows(ui, row_height, rows, |ui, range| {
+                for row in range {
+                    ui.horizontal(|ui| {
+                        let start = row * self.key_length;
+                        let end = (start + self.key_length).min(cells.len());
+                        for (position, cell) in cells[start..end].iter().enumerate() {
+                            let (text, color) = cell.text(ui.visuals().text_color());
+                            let text = RichText::new(text)
+                                .monospace()
+                                .color(color)
+                                .background_color(self.background(position));
+                            let label = Label::new(text).sense(Sense::click());
+                            if ui.add_sized(cell_size, label).clicked() {
+                                clicked = Some(position);
+                            }
+                        }
+                    });
+                }
+            });
+        });
+
+        clicked
+    }
+
+    /// Current key byte of the column coloured by its state.
+    fn header(&self, position: usize) -> (String, Color32) {
+        let Some(key) = self.key.filter(|key| position < key.len()) else {
+            return ("--".to_owned(), Color32::GRAY);
+        };
+
+        let text = key
+            .get_current_value(position)
+            .map_or_else(|| "!!".to_owned(), |value| format!("{:02X}", value));
+        let color = if key.is_error(position) {
+            Color32::LIGHT_RED
+        } else if key.is_decoded(position) {
+            Color32::LIGHT_GREEN
+        } else if !key.is_uncertain(position) {
+            Color32::YELLOW
+        } else {
+            Color32::GRAY
+        };
+        (text, color)
+    }
+
+    fn background(&self, position: usize) -> Color32 {
+        if self.selected == Some(position) {
+            Color32::DARK_BLUE
+        } else {
+            Color32::TRANSPARENT
+        }
+    }
+}
+
+impl Cell {
+    /// Cells of every byte of the decoded characters.
+    pub(super) fn from_characters(characters: &[DecodedCharacter]) -> Vec<Self> {
+        let mut cells = Vec::new();
+        for character in characters {
+            for offset in character.bytes.clone() {
+                cells.push(if !character.valid {
+                    Self::Invalid
+                } else if offset == character.bytes.start {
+                    Self::Character(character.character)
+                } else {
+                    Self::Continuation
+                });
+            }
+        }
+        cells
+    }
+
+    /// Visible text of the cell and its colour, valid characters use the given colour.
+    pub(super) fn text(&self, text_color: Color32) -> (String, Color32) {
+        match self {
+            Self::Character(character) => (visible(*character), text_color),
+            Self::Continuation => ("·".to_owned(), Color32::GRAY),
+            Self::Invalid => (char::REPLACEMENT_CHARACTER.to_string(), Color32::RED),
+        }
+    }
+}
+
+/// Show control characters and no-break space as visible escapes, fitting the monospace font.
+fn visible(character: char) -> String {
+    match character {
+        '\r' => "\\r".to_owned(),
+        '\n' => "\\n".to_owned(),
+        '\t' => "\\t".to_owned(),
+        // Tilde is the no-break space of TeX.
+        '\u{a0}' => "~".to_owned(),
+        '\0'..='\u{1f}' | '\u{7f}' => format!("^{}", ((character as u8) ^ 0x40) as char),
+        character if character.is_control() => format!("{:02X}", character as u32),
+        character => character.to_string(),
+    }
+}
@@ -0,0 +1,131 @@
+use super::grid_view::Cell;
+use crate::PotentialKey;
+use egui::text::LayoutJob;
+use egui::{Color32, Label, ScrollArea, Sense, TextFormat, TextStyle, Ui};
+
+/// Ciphertext, key and plaintext bytes listed side by side, one row per offset.
+pub struct HexView<'a> {
+    ciphertext: &'a [u8],
+    plaintext: &'a [u8],
+    cipher_key: &'a [u8],
+    cells: &'a [Cell],
+    key: Option<&'a PotentialKey>,
+    key_length: usize,
+    selected: Option<usize>,
+    scroll_to: Option<usize>,
+}
+
+impl<'a> HexView<'a> {
+    pub(super) fn new(
+        ciphertext: &'a [u8],
+        plaintext: &'a [u8],
+        cipher_key: &'a [u8],
+        cells: &'a [Cell],
+    ) -> Self {
+        Self {
+            ciphertext,
+            plaintext,
+            cipher_key,
+            cells,
+            key: None,
+            key_length: cipher_key.len().max(1),
+            selected: None,
+            scroll_to: None,
+        }
+    }
+
+    /// Colour key bytes by the state of the potential key.
+    pub fn key(mut self, key: Option<&'a PotentialKey>) -> Self {
+        self.key = key;
+        self
+    }
+
+    /// Highlight rows of the selected key position.
+    pub fn selected(mut self, key_length: usize, selected: Option<usize>) -> Self {
+        self.key_length = key_length.max(1);
+        self.selected = selected;
+        self
+    }
+
+    /// Scroll so the row of the given offset is on top.
+    pub fn scroll_to(mut self, offset: Option<usize>) -> Self {
+        self.scroll_to = offset;
+        self
+    }
+
+    /// Show the rows, returns the key position of the clicked row.
+    pub fn show(self, ui: &mut Ui) -> Option<usize> {
+        let cells = self.cells;
+        let font = TextStyle::Monospace.resolve(ui.style());
+        let text_color = ui.visuals().text_color();
+        let row_height = ui.text_style_height(&TextStyle::Monospace);
+        let rows = self.ciphertext.len().min(self.plaintext.len());
+        let mut clicked = None;
+
+        let mut scroll_area = ScrollArea::vertical().auto_shrink([false; 2]);
+        if let Some(offset) = self.scroll_to {
+            let spacing = ui.spacing().item_spacing.y;
+            scroll_area =
+                scroll_area.vertical_scroll_offset(offset as f32 * (row_height + spacing));
+        }
+
+        scroll_area.show_rows(ui, row_height, rows, |ui, range| {
+            for offset in range {
+                let position = offset % self.key_length;
+                let background = if self.selected == Some(position) {
+                    Color32::DARK_BLUE
+                } else {
+                    Color32::TRANSPARENT
+                };
+                let format = |color| TextFormat {
+                    font_id: font.clone(),
+                    color,
+                    background,
+                    ..Default::default()
+                };
+
+                let mut job = LayoutJob::default();
+                job.append(&format!("{:08X}", offset), 0.0, format(Color32::GRAY));
+                job.append(
+                    &format!("  {:02X}", self.ciphertext[offset]),
+                    0.0,
+                    format(text_color),
+                );
+                let key_byte = self.cipher_key[offset % self.cipher_key.len()];
+                job.append(
+                    &format!("  {:02X}", key_byte),
+                    0.0,
+                    format(self.key_color(position, text_color)),
+                );
+                job.append(
+                    &format!("  {:02X}  ", self.plaintext[offset]),
+                    0.0,
+                    format(text_color),
+                );
+                let (glyph, color) = cells
+                    .get(offset)
+                    .map_or_else(|| (String::new(), text_color), |cell| cell.text(text_color));
+                job.append(&glyph, 0.0, format(color));
+
+                let response = ui
+                    .add(Label::new(job).sense(Sense::click()))
+                    .on_hover_text(format!("Key position {}", position));
+                if response.clicked() {
+                    clicked = Some(position);
+                }
+            }
+        });
+
+        clicked
+    }
+
+    /// Colour of the key byte, the same as in the key buttons.
+    fn key_color(&self, position: usize, text_color: Color32) -> Color32 {
+        match self.key.filter(|key| position < key.len()) {
+            Some(key) if key.is_decoded(position) => Color32::LIGHT_GREEN,
+            Some(key) if !key.is_uncertain(position) => Color32::YELLOW,
+            Some(key) if key.is_error(position) => Color32::LIGHT_RED,
+            _ => text_color,
+        }
+    }
+}
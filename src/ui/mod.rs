@@ -1,5 +1,11 @@
 mod application;
+mod crack_task;
+mod grid_view;
+mod hex_view;
 mod usize_input;
 
 pub use application::Application;
+pub use crack_task::*;
+pub use grid_view::*;
+pub use hex_view::*;
 pub use usize_input::*;
@@ -0,0 +1,102 @@
+/// A multi-pattern matcher finding every word at every offset of a text in a single pass.
+///
+/// It is an Aho-Corasick automaton over sets of bytes: every text offset allows a set of
+/// plaintext bytes, the ones possible under the key, so instead of a single state with failure
+/// links the search keeps every trie node whose path fits the text ending at the current offset.
+pub struct WordMatcher {
+    nodes: Vec<Node>,
+    longest: usize,
+}
+
+/// A trie node, the root is the first node.
+#[derive(Default)]
+struct Node {
+    /// Next byte of a word and the node it leads to.
+    edges: Vec<(u8, u32)>,
+    /// Words ending at this node with their length.
+    words: Vec<(usize, usize)>,
+}
+
+/// State of a search in a text, fed one offset at a time.
+pub struct WordSearch<'a> {
+    matcher: &'a WordMatcher,
+    active: Vec<u32>,
+    next: Vec<u32>,
+    offset: usize,
+}
+
+impl WordMatcher {
+    /// Create a matcher of the words, matches report the index of the word in the slice.
+    /// Empty words never match.
+    pub fn new(words: &[Vec<u8>]) -> Self {
+        let mut nodes = vec![Node::default()];
+        let mut longest = 0;
+
+        for (index, word) in words.iter().enumerate() {
+            if word.is_empty() {
+                continue;
+            }
+            let mut node = 0;
+            for byte in word {
+                node = match nodes[node].edges.iter().find(|(edge, _)| edge == byte) {
+                    Some((_, child)) => *child as usize,
+                    None => {
+                        nodes.push(Node::default());
+                        let child = nodes.len() - 1;
+                        nodes[node].edges.push((*byte, child as u32));
+                        child
+                    }
+                };
+            }
+            nodes[node].words.push((index, word.len()));
+            longest = longest.max(word.len());
+        }
+
+        Self { nodes, longest }
+    }
+
+    /// Length of the longest word.
+    pub fn longest(&self) -> usize {
+        self.longest
+    }
+
+    /// Start a search at the beginning of a text.
+    pub fn search(&self) -> WordSearch<'_> {
+        self.search_at(0)
+    }
+
+    /// Start a search at the offset of a text, words starting before it are not found.
+    pub fn search_at(&self, offset: usize) -> WordSearch<'_> {
+        WordSearch {
+            matcher: self,
+            active: Vec::new(),
+            next: Vec::new(),
+            offset,
+        }
+    }
+}
+
+impl WordSearch<'_> {
+    /// Advance by one text offset where the plaintext may be any byte `allowed` accepts.
+    /// Calls `found` with the word index and start offset of every word ending here.
+    pub fn push(&mut self, allowed: impl Fn(u8) -> bool, mut found: impl FnMut(usize, usize)) {
+        let nodes = &self.matcher.nodes;
+
+        // A word may start at every offset, so the root is always active.
+        self.active.push(0);
+        self.next.clear();
+        for node in &self.active {
+            for (byte, child) in &nodes[*node as usize].edges {
+                if allowed(*byte) {
+                    self.next.push(*child);
+                    for (word, length) in &nodes[*child as usize].words {
+                        found(*word, self.offset + 1 - length);
+                    }
+                }
+            }
+        }
+
+        std::mem::swap(&mut self.active, &mut self.next);
+        self.offset += 1;
+    }
+}